@@ -0,0 +1,105 @@
+//! Compares the broadphases on the same scenes.
+//!
+//! Run with `cargo bench --bench broadphase`.
+
+use std::time::{Duration, Instant};
+
+use physics_engine::{BroadphaseKind, SimulationConfig, Solver, Vertex};
+
+const KINDS: [BroadphaseKind; 4] = [
+    BroadphaseKind::Grid,
+    BroadphaseKind::HashGrid,
+    BroadphaseKind::SweepAndPrune,
+    BroadphaseKind::Quadtree,
+];
+const FRAMES: u32 = 20;
+
+struct Scene {
+    name: &'static str,
+    config: SimulationConfig,
+    objects: Vec<Vertex>,
+}
+
+fn color() -> cgmath::Vector3<f32> {
+    cgmath::vec3(1.0, 1.0, 1.0)
+}
+
+/// Same-size particles falling as one dense block, the case the grid is built for.
+fn uniform_pile() -> Scene {
+    let config = SimulationConfig::new(cgmath::vec2(300.0, 300.0), 1.0);
+    let objects = (0..10_000)
+        .map(|i| {
+            let position =
+                cgmath::vec2(10.0 + (i % 100) as f32 * 2.0, 10.0 + (i / 100) as f32 * 2.0);
+            Vertex::new(position, color())
+        })
+        .collect();
+    Scene {
+        name: "uniform pile",
+        config,
+        objects,
+    }
+}
+
+/// Grains with a few boulders, which blow up the grid cell size.
+fn mixed_sizes() -> Scene {
+    let config = SimulationConfig::new(cgmath::vec2(300.0, 300.0), 1.0);
+    let objects = (0..4_000)
+        .map(|i| {
+            let position = cgmath::vec2(10.0 + (i % 80) as f32 * 3.5, 10.0 + (i / 80) as f32 * 3.5);
+            let radius = if i % 97 == 0 { 12.0 } else { 1.0 };
+            Vertex::new(position, color()).with_radius(radius)
+        })
+        .collect();
+    Scene {
+        name: "mixed sizes",
+        config,
+        objects,
+    }
+}
+
+/// Few particles spread over a large world with no gravity.
+fn sparse_world() -> Scene {
+    let mut config = SimulationConfig::new(cgmath::vec2(4000.0, 4000.0), 1.0);
+    config.gravity = cgmath::vec2(0.0, 0.0);
+    let objects = (0..5_000)
+        .map(|i| {
+            let position = cgmath::vec2(
+                10.0 + (i * 7919 % 3980) as f32,
+                10.0 + (i * 104_729 % 3980) as f32,
+            );
+            Vertex::new(position, color())
+        })
+        .collect();
+    Scene {
+        name: "sparse world",
+        config,
+        objects,
+    }
+}
+
+fn run(scene: &Scene, kind: BroadphaseKind) -> Duration {
+    let config = SimulationConfig {
+        broadphase: kind,
+        ..scene.config.clone()
+    };
+    let mut solver = Solver::new(config, scene.objects.clone());
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        solver.update(1.0 / 60.0);
+    }
+    start.elapsed() / FRAMES
+}
+
+fn main() {
+    for scene in [uniform_pile(), mixed_sizes(), sparse_world()] {
+        println!("{} ({} particles)", scene.name, scene.objects.len());
+        for kind in KINDS {
+            println!(
+                "  {:<16} {:>10.3?} per frame",
+                format!("{kind:?}"),
+                run(&scene, kind)
+            );
+        }
+    }
+}
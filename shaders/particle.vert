@@ -1,14 +1,15 @@
 #version 330 core
 layout (location = 0) in vec2 pos;
 layout (location = 1) in vec3 color;
+layout (location = 2) in float radius;
 
 out vec3 PointColor;
 
 uniform mat4 projection;
+uniform float pixels_per_unit;
 
 void main() {
     gl_Position = projection * vec4(pos, 0.0, 1.0);
-    gl_PointSize = 10;
-    //    gl_PointSize = 30;
+    gl_PointSize = 2.0 * radius * pixels_per_unit;
     PointColor = color;
 }
@@ -0,0 +1,53 @@
+use crate::config::SimulationConfig;
+use crate::grid::{DensestCell, Grid};
+use crate::hash_grid::HashGrid;
+use crate::quadtree::LooseQuadtree;
+use crate::sweep_and_prune::SweepAndPrune;
+use crate::vertex::Vertex;
+
+/// Finds pairs of objects that may be touching.
+///
+/// Implementations must report every pair whose bounding boxes overlap, each pair once as
+/// `(i, j)` with `i < j`, in an order that only depends on the objects so that deterministic
+/// runs stay deterministic. Reporting pairs that do not touch is fine.
+pub trait Broadphase: Send + Sync {
+    /// Indexes `objects`, the object id is its index in the slice.
+    fn build(&mut self, objects: &[Vertex]);
+
+    /// Replaces the contents of `pairs` with the candidate pairs of the last build.
+    fn pairs(&self, pairs: &mut Vec<(usize, usize)>);
+
+    /// The most populated cell of the last build, for broadphases that have cells.
+    fn densest_cell(&self) -> DensestCell {
+        DensestCell::default()
+    }
+}
+
+/// Broadphase the solver builds from the config. Only the dense grid finds pairs across
+/// periodic axes, so worlds with one always use it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BroadphaseKind {
+    /// Dense [`Grid`] over `[0, world_size]`, objects outside of it never collide.
+    #[default]
+    Grid,
+    /// [`HashGrid`] without bounds, for open worlds and negative coordinates.
+    HashGrid,
+    /// [`SweepAndPrune`] along x, for mixed sizes and sparse worlds.
+    SweepAndPrune,
+    /// [`LooseQuadtree`], for mixed sizes and clustered worlds.
+    Quadtree,
+}
+
+impl BroadphaseKind {
+    /// Builds the broadphase, falling back to the dense grid if an axis is periodic.
+    pub fn create(self, config: &SimulationConfig) -> Box<dyn Broadphase> {
+        let periodic = config.periodic.x || config.periodic.y;
+        match self {
+            BroadphaseKind::Grid => Box::new(Grid::new(config)),
+            _ if periodic => Box::new(Grid::new(config)),
+            BroadphaseKind::HashGrid => Box::new(HashGrid::new(config.cell_width)),
+            BroadphaseKind::SweepAndPrune => Box::new(SweepAndPrune::new()),
+            BroadphaseKind::Quadtree => Box::new(LooseQuadtree::new()),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use cgmath::{vec2, InnerSpace, Vector2};
+
+/// Geometry of a static collider.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Two-sided line segment.
+    Segment {
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+    },
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    /// Convex polygon with counter-clockwise points.
+    Polygon {
+        points: Vec<Vector2<f32>>,
+    },
+}
+
+impl Shape {
+    /// Signed distance from `point` to the shape surface and the outward surface normal
+    /// at the closest point. The distance is negative inside circles and polygons.
+    pub fn signed_distance(&self, point: Vector2<f32>) -> (f32, Vector2<f32>) {
+        match self {
+            Shape::Segment { a, b } => {
+                let closest = closest_on_segment(point, *a, *b);
+                let offset = point - closest;
+                let distance = offset.magnitude();
+                if distance > 0.0 {
+                    (distance, offset / distance)
+                } else if a != b {
+                    let edge = (*b - *a).normalize();
+                    (0.0, vec2(-edge.y, edge.x))
+                } else {
+                    (0.0, vec2(0.0, 1.0))
+                }
+            }
+            Shape::Circle { center, radius } => {
+                let offset = point - *center;
+                let distance = offset.magnitude();
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    vec2(0.0, 1.0)
+                };
+                (distance - radius, normal)
+            }
+            Shape::Polygon { points } => polygon_distance(points, point),
+        }
+    }
+
+    /// Axis-aligned bounding box as `(min, max)`.
+    pub fn bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+        match self {
+            Shape::Segment { a, b } => (
+                vec2(a.x.min(b.x), a.y.min(b.y)),
+                vec2(a.x.max(b.x), a.y.max(b.y)),
+            ),
+            Shape::Circle { center, radius } => (
+                vec2(center.x - radius, center.y - radius),
+                vec2(center.x + radius, center.y + radius),
+            ),
+            Shape::Polygon { points } => {
+                let mut min = vec2(f32::MAX, f32::MAX);
+                let mut max = vec2(f32::MIN, f32::MIN);
+                for point in points {
+                    min = vec2(min.x.min(point.x), min.y.min(point.y));
+                    max = vec2(max.x.max(point.x), max.y.max(point.y));
+                }
+                (min, max)
+            }
+        }
+    }
+}
+
+/// Immovable shape particles collide with, such as ramps, pegs and platforms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collider {
+    pub shape: Shape,
+    /// Combined with the particle friction as `sqrt(a * b)`.
+    pub friction: f32,
+    /// Combined with the particle restitution as `max(a, b)`.
+    pub restitution: f32,
+    /// Scales the particle adhesion, `0` keeps particles from sticking to the collider.
+    pub adhesion: f32,
+}
+
+impl Collider {
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            friction: 0.0,
+            restitution: 0.0,
+            adhesion: 1.0,
+        }
+    }
+
+    pub fn segment(a: Vector2<f32>, b: Vector2<f32>) -> Self {
+        Self::new(Shape::Segment { a, b })
+    }
+
+    pub fn circle(center: Vector2<f32>, radius: f32) -> Self {
+        Self::new(Shape::Circle { center, radius })
+    }
+
+    /// Axis-aligned box between `min` and `max`.
+    pub fn aabb(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self::polygon(vec![min, vec2(max.x, min.y), max, vec2(min.x, max.y)])
+    }
+
+    /// Box rotated counter-clockwise by `angle` radians around its `center`.
+    pub fn rotated_box(center: Vector2<f32>, half_extents: Vector2<f32>, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let rotate = |x: f32, y: f32| center + vec2(x * cos - y * sin, x * sin + y * cos);
+        let (hx, hy) = (half_extents.x, half_extents.y);
+        Self::polygon(vec![
+            rotate(-hx, -hy),
+            rotate(hx, -hy),
+            rotate(hx, hy),
+            rotate(-hx, hy),
+        ])
+    }
+
+    /// Convex polygon, clockwise point order is reversed.
+    pub fn polygon(mut points: Vec<Vector2<f32>>) -> Self {
+        let doubled_area: f32 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.x * b.y - b.x * a.y)
+            .sum();
+        if doubled_area < 0.0 {
+            points.reverse();
+        }
+        Self::new(Shape::Polygon { points })
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_adhesion(mut self, adhesion: f32) -> Self {
+        self.adhesion = adhesion;
+        self
+    }
+}
+
+/// Colliders bucketed into a coarse hash grid, so each particle only tests the colliders
+/// near it no matter how large the level is.
+#[derive(Debug, Default)]
+pub struct ColliderSet {
+    colliders: Vec<Collider>,
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    /// Distance the buckets cover around each collider, the largest particle radius.
+    margin: f32,
+    dirty: bool,
+}
+
+impl ColliderSet {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, collider: Collider) -> usize {
+        self.colliders.push(collider);
+        self.dirty = true;
+        self.colliders.len() - 1
+    }
+
+    pub fn get_colliders(&self) -> &[Collider] {
+        &self.colliders
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colliders.is_empty()
+    }
+
+    /// Rebuilds the buckets if colliders were added or particles grew beyond `margin`.
+    pub(crate) fn prepare(&mut self, margin: f32) {
+        if !self.dirty && margin <= self.margin {
+            return;
+        }
+        self.margin = margin;
+        self.dirty = false;
+        self.cells.clear();
+        for (index, collider) in self.colliders.iter().enumerate() {
+            let (min, max) = collider.shape.bounds();
+            let (min_x, min_y) = self.cell_coords(min - vec2(margin, margin));
+            let (max_x, max_y) = self.cell_coords(max + vec2(margin, margin));
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    self.cells.entry((x, y)).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    /// Indices of colliders that may touch a particle at `position`.
+    pub fn candidates(&self, position: Vector2<f32>) -> &[usize] {
+        self.cells
+            .get(&self.cell_coords(position))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn cell_coords(&self, position: Vector2<f32>) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+fn closest_on_segment(point: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>) -> Vector2<f32> {
+    let edge = b - a;
+    let length2 = edge.magnitude2();
+    if length2 == 0.0 {
+        return a;
+    }
+    let t = ((point - a).dot(edge) / length2).clamp(0.0, 1.0);
+    a + edge * t
+}
+
+fn polygon_distance(points: &[Vector2<f32>], point: Vector2<f32>) -> (f32, Vector2<f32>) {
+    let mut max_separation = f32::MIN;
+    let mut face_normal = vec2(0.0, 1.0);
+    let mut min_distance2 = f32::MAX;
+    let mut closest = point;
+    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+        let edge = *b - *a;
+        if edge.magnitude2() == 0.0 {
+            continue;
+        }
+        let normal = vec2(edge.y, -edge.x).normalize();
+        let separation = (point - *a).dot(normal);
+        if separation > max_separation {
+            max_separation = separation;
+            face_normal = normal;
+        }
+        let candidate = closest_on_segment(point, *a, *b);
+        let distance2 = (point - candidate).magnitude2();
+        if distance2 < min_distance2 {
+            min_distance2 = distance2;
+            closest = candidate;
+        }
+    }
+    if max_separation <= 0.0 {
+        // Inside: leave through the nearest face.
+        return (max_separation, face_normal);
+    }
+    let distance = min_distance2.sqrt();
+    if distance > 0.0 {
+        (distance, (point - closest) / distance)
+    } else {
+        (0.0, face_normal)
+    }
+}
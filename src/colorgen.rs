@@ -25,3 +25,9 @@ impl ColorGenerator {
         cgmath::vec3(r, g, b)
     }
 }
+
+impl Default for ColorGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
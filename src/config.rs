@@ -0,0 +1,150 @@
+use cgmath::Vector2;
+
+use crate::broadphase::BroadphaseKind;
+use crate::fluid::Fluid;
+use crate::gravity::{NBody, PointGravity};
+
+/// A value for each wall of the world box.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Walls {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl Walls {
+    pub fn all(value: f32) -> Self {
+        Self {
+            left: value,
+            right: value,
+            bottom: value,
+            top: value,
+        }
+    }
+}
+
+/// Axes on which particles leaving one side of the world re-enter on the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Periodic {
+    pub x: bool,
+    pub y: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationConfig {
+    pub world_size: Vector2<f32>,
+    /// Radius of particles spawned by the engine.
+    pub radius: f32,
+    pub gravity: Vector2<f32>,
+    /// Pulls towards a center instead of along `gravity` when set.
+    pub point_gravity: Option<PointGravity>,
+    /// Makes particles attract each other, on top of the gravity above.
+    pub n_body: Option<NBody>,
+    /// Parameters shared by all fluid particles.
+    pub fluid: Fluid,
+    pub sub_steps: u32,
+    /// Fixed simulation step used by the engine, in seconds.
+    pub time_step: f32,
+    /// How many fixed steps the engine may run per frame to catch up with the wall clock.
+    pub max_steps_per_frame: u32,
+    /// Fraction of velocity lost per second.
+    pub linear_damping: f32,
+    /// Quadratic air drag coefficient, the deceleration is `air_drag * |v|^2`.
+    pub air_drag: f32,
+    /// Friction coefficient of the world walls, combined with the particle friction.
+    pub wall_friction: f32,
+    /// Bounciness of each wall, a collision uses the larger of the wall and particle values.
+    pub wall_restitution: Walls,
+    /// Scales the particle adhesion at the world walls and containers.
+    pub wall_adhesion: f32,
+    /// Gap beyond contact up to which cohesion and adhesion pull, `0` turns both off.
+    pub cohesion_range: f32,
+    /// Wrap-around axes, the walls across them are removed.
+    pub periodic: Periodic,
+    /// Minimal grid cell size, the solver grows it to fit the largest particle and the
+    /// cohesion range.
+    pub cell_width: f32,
+    pub broadphase: BroadphaseKind,
+    /// Updates between spatial sorts of the particle storage, `0` never sorts. Sorting keeps
+    /// neighbours close in memory but changes particle indices, handles stay valid.
+    pub sort_interval: u32,
+    /// Number of solver threads, `0` shares the global rayon pool with other solvers.
+    pub threads: usize,
+    /// Makes results independent of `threads` at the cost of a finer stripe layout.
+    pub deterministic: bool,
+}
+
+impl SimulationConfig {
+    pub fn new(world_size: Vector2<f32>, radius: f32) -> Self {
+        Self {
+            world_size,
+            radius,
+            gravity: cgmath::vec2(0.0, -1000.0),
+            point_gravity: None,
+            n_body: None,
+            fluid: Fluid::new(radius),
+            sub_steps: 8,
+            time_step: 1.0 / 60.0,
+            max_steps_per_frame: 5,
+            linear_damping: 0.0,
+            air_drag: 0.0,
+            wall_friction: 0.0,
+            wall_restitution: Walls::default(),
+            wall_adhesion: 1.0,
+            cohesion_range: 0.0,
+            periodic: Periodic::default(),
+            cell_width: radius * 2.0,
+            broadphase: BroadphaseKind::default(),
+            sort_interval: 60,
+            threads: 0,
+            deterministic: false,
+        }
+    }
+
+    pub fn grid_width(&self) -> usize {
+        Self::cell_count(self.world_size.x, self.cell_width, self.periodic.x)
+    }
+
+    pub fn grid_height(&self) -> usize {
+        Self::cell_count(self.world_size.y, self.cell_width, self.periodic.y)
+    }
+
+    /// Size of a grid cell. Periodic axes stretch `cell_width` so that the cells tile the
+    /// world exactly and the neighbours across the seam are a full cell away.
+    pub fn cell_size(&self) -> Vector2<f32> {
+        let mut size = cgmath::vec2(self.cell_width, self.cell_width);
+        if self.periodic.x {
+            size.x = self.world_size.x / self.grid_width() as f32;
+        }
+        if self.periodic.y {
+            size.y = self.world_size.y / self.grid_height() as f32;
+        }
+        size
+    }
+
+    /// Shortest displacement equivalent to `offset` once periodic axes are wrapped.
+    pub fn minimum_image(&self, mut offset: Vector2<f32>) -> Vector2<f32> {
+        if self.periodic.x {
+            offset.x -= self.world_size.x * (offset.x / self.world_size.x).round();
+        }
+        if self.periodic.y {
+            offset.y -= self.world_size.y * (offset.y / self.world_size.y).round();
+        }
+        offset
+    }
+
+    fn cell_count(size: f32, cell_width: f32, periodic: bool) -> usize {
+        if periodic {
+            ((size / cell_width).floor() as usize).max(1)
+        } else {
+            (size / cell_width).ceil() as usize
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self::new(cgmath::vec2(300.0, 300.0), 1.0)
+    }
+}
@@ -0,0 +1,136 @@
+use cgmath::{vec2, InnerSpace, Vector2};
+
+/// Signed distance to a container wall, negative inside.
+pub type Sdf = Box<dyn Fn(Vector2<f32>) -> f32 + Send + Sync>;
+
+/// Region particles are kept inside of.
+///
+/// Everything except [`Container::Walls`] uses `wall_friction` from the config and the
+/// particle restitution. The container should fit inside `world_size`, particles outside
+/// the grid do not collide with each other.
+#[derive(Default)]
+pub enum Container {
+    /// The `[0, world_size]` box with per-wall restitution.
+    #[default]
+    Walls,
+    /// No walls at all, for open worlds with [`crate::BroadphaseKind::HashGrid`].
+    Open,
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    /// Stadium shape around the segment from `a` to `b`.
+    Capsule {
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+        radius: f32,
+    },
+    /// Box spinning around its center, at `angle` radians when the solver time is zero.
+    RotatingBox {
+        center: Vector2<f32>,
+        half_extents: Vector2<f32>,
+        angle: f32,
+        angular_velocity: f32,
+    },
+    Sdf(Sdf),
+}
+
+/// Contact of a particle with a container wall.
+pub(crate) struct Contact {
+    /// Points into the container.
+    pub normal: Vector2<f32>,
+    pub depth: f32,
+    pub surface_velocity: Vector2<f32>,
+}
+
+impl Container {
+    /// Step used to estimate the gradient of a user distance function.
+    const GRADIENT_STEP: f32 = 0.01;
+
+    pub fn sdf(sdf: impl Fn(Vector2<f32>) -> f32 + Send + Sync + 'static) -> Self {
+        Container::Sdf(Box::new(sdf))
+    }
+
+    /// Calls `contact` for every wall a particle at `position` penetrates at `time`.
+    pub(crate) fn contacts(
+        &self,
+        position: Vector2<f32>,
+        radius: f32,
+        time: f32,
+        mut contact: impl FnMut(Contact),
+    ) {
+        match self {
+            Container::Walls | Container::Open => {}
+            Container::Circle { center, radius: r } => {
+                if let Some(found) = round_contact(position - *center, *r - radius) {
+                    contact(found);
+                }
+            }
+            Container::Capsule { a, b, radius: r } => {
+                let edge = *b - *a;
+                let t = if edge.magnitude2() > 0.0 {
+                    ((position - *a).dot(edge) / edge.magnitude2()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let offset = position - (*a + edge * t);
+                if let Some(found) = round_contact(offset, *r - radius) {
+                    contact(found);
+                }
+            }
+            Container::RotatingBox {
+                center,
+                half_extents,
+                angle,
+                angular_velocity,
+            } => {
+                let (sin, cos) = (angle + angular_velocity * time).sin_cos();
+                let axes = [vec2(cos, sin), vec2(-sin, cos)];
+                let offset = position - *center;
+                let surface_velocity = vec2(-offset.y, offset.x) * *angular_velocity;
+                for (axis, half_extent) in axes.into_iter().zip([half_extents.x, half_extents.y]) {
+                    let local = offset.dot(axis);
+                    let depth = local.abs() + radius - half_extent;
+                    if depth > 0.0 {
+                        contact(Contact {
+                            normal: -axis * local.signum(),
+                            depth,
+                            surface_velocity,
+                        });
+                    }
+                }
+            }
+            Container::Sdf(sdf) => {
+                let distance = sdf(position);
+                let depth = distance + radius;
+                if depth > 0.0 {
+                    let h = Self::GRADIENT_STEP;
+                    let gradient = vec2(
+                        sdf(position + vec2(h, 0.0)) - sdf(position - vec2(h, 0.0)),
+                        sdf(position + vec2(0.0, h)) - sdf(position - vec2(0.0, h)),
+                    );
+                    if gradient.magnitude2() > 0.0 {
+                        contact(Contact {
+                            normal: -gradient.normalize(),
+                            depth,
+                            surface_velocity: vec2(0.0, 0.0),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Contact with the inside of a circle of `inner_radius` around the origin of `offset`.
+fn round_contact(offset: Vector2<f32>, inner_radius: f32) -> Option<Contact> {
+    let distance = offset.magnitude();
+    if distance <= inner_radius || distance == 0.0 {
+        return None;
+    }
+    Some(Contact {
+        normal: -offset / distance,
+        depth: distance - inner_radius,
+        surface_velocity: vec2(0.0, 0.0),
+    })
+}
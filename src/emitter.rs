@@ -0,0 +1,257 @@
+use std::f32::consts::TAU;
+
+use cgmath::{Basis2, Rad, Rotation, Rotation2, Vector2, Vector3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::colorgen::ColorGenerator;
+use crate::particle_id::Handle;
+use crate::vertex::Vertex;
+
+/// Stable handle of an emitter, unaffected by finished emitters being dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EmitterId {
+    slot: u32,
+    generation: u32,
+}
+
+impl Handle for EmitterId {
+    fn from_parts(slot: u32, generation: u32) -> Self {
+        Self { slot, generation }
+    }
+
+    fn parts(self) -> (u32, u32) {
+        (self.slot, self.generation)
+    }
+}
+
+/// Region new particles are placed in, uniformly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterShape {
+    Point(Vector2<f32>),
+    Line {
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+    },
+    Disk {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    Area {
+        min: Vector2<f32>,
+        max: Vector2<f32>,
+    },
+}
+
+impl EmitterShape {
+    fn sample(&self, rng: &mut StdRng) -> Vector2<f32> {
+        match *self {
+            EmitterShape::Point(position) => position,
+            EmitterShape::Line { a, b } => a + (b - a) * rng.gen::<f32>(),
+            EmitterShape::Disk { center, radius } => {
+                let distance = radius * rng.gen::<f32>().sqrt();
+                let angle = rng.gen::<f32>() * TAU;
+                center + cgmath::vec2(angle.cos(), angle.sin()) * distance
+            }
+            EmitterShape::Area { min, max } => {
+                min + cgmath::vec2(
+                    (max.x - min.x) * rng.gen::<f32>(),
+                    (max.y - min.y) * rng.gen::<f32>(),
+                )
+            }
+        }
+    }
+}
+
+/// Where emitted particles get their color from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorSource {
+    /// Keeps the color of the template.
+    #[default]
+    Template,
+    Fixed(Vector3<f32>),
+    /// Steps through a rainbow, one color per particle.
+    Cycle,
+}
+
+/// When an enabled emitter runs, in simulation time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Schedule {
+    #[default]
+    Always,
+    /// Emits from `start` until `end` seconds.
+    Between { start: f32, end: f32 },
+    /// Emits for `on` seconds, pauses for `off` seconds and repeats.
+    Pulse { on: f32, off: f32 },
+}
+
+impl Schedule {
+    pub fn is_on(&self, time: f32) -> bool {
+        match *self {
+            Schedule::Always => true,
+            Schedule::Between { start, end } => start <= time && time < end,
+            Schedule::Pulse { on, off } => time.rem_euclid(on + off) < on,
+        }
+    }
+}
+
+/// Spawns copies of a template particle over time.
+///
+/// Emission is driven by the solver at the start of every update. Positions and launch
+/// directions come from a seeded generator, so runs with the same seed repeat exactly.
+/// Emitters without a seed of their own get one from the solver, different for every
+/// emitter it was given.
+pub struct Emitter {
+    pub shape: EmitterShape,
+    /// Particles per second. An infinite rate emits everything up to `max_count` at once,
+    /// but never more than [`Emitter::MAX_PER_UPDATE`].
+    pub rate: f32,
+    /// Launch velocity of every particle.
+    pub velocity: Vector2<f32>,
+    /// Launch directions are turned by up to this many radians either way.
+    pub spread: f32,
+    /// Copied for every particle, the emitter only sets position, velocity and color.
+    pub template: Vertex,
+    pub color: ColorSource,
+    /// The emitter is finished once it emitted this many particles.
+    pub max_count: Option<usize>,
+    pub schedule: Schedule,
+    /// A disabled emitter keeps its state but emits nothing.
+    pub enabled: bool,
+    emitted: usize,
+    /// Fraction of a particle carried over to the next update.
+    pending: f32,
+    rng: StdRng,
+    /// Whether `rng` was seeded with [`Emitter::with_seed`].
+    seeded: bool,
+    colors: ColorGenerator,
+}
+
+impl Emitter {
+    /// Most particles spawned in one update. Anything due beyond it is dropped, so huge
+    /// rates without a `max_count` cannot pile up an endless backlog.
+    pub const MAX_PER_UPDATE: usize = 10_000;
+
+    pub fn new(shape: EmitterShape, rate: f32) -> Self {
+        Self {
+            shape,
+            rate,
+            velocity: cgmath::vec2(0.0, 0.0),
+            spread: 0.0,
+            template: Vertex::new(cgmath::vec2(0.0, 0.0), cgmath::vec3(1.0, 1.0, 1.0)),
+            color: ColorSource::default(),
+            max_count: None,
+            schedule: Schedule::default(),
+            enabled: true,
+            emitted: 0,
+            pending: 0.0,
+            rng: StdRng::seed_from_u64(0),
+            seeded: false,
+            colors: ColorGenerator::new(),
+        }
+    }
+
+    /// Emits `count` particles on the next update, spread over several updates if there
+    /// are more than [`Emitter::MAX_PER_UPDATE`], and then finishes.
+    pub fn burst(shape: EmitterShape, count: usize) -> Self {
+        Self::new(shape, f32::INFINITY).with_max_count(count)
+    }
+
+    pub fn with_velocity(mut self, velocity: Vector2<f32>) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_template(mut self, template: Vertex) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn with_color(mut self, color: ColorSource) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.seeded = true;
+        self
+    }
+
+    /// Seeds the generator unless [`Emitter::with_seed`] already did.
+    pub(crate) fn seed_default(&mut self, seed: u64) {
+        if !self.seeded {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+    }
+
+    /// Number of particles emitted so far.
+    pub fn emitted(&self) -> usize {
+        self.emitted
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.max_count
+            .is_some_and(|max_count| self.emitted >= max_count)
+    }
+
+    /// Emits the particles due over `dt` starting at `time`, handing each one to `spawn`
+    /// together with its launch velocity.
+    pub(crate) fn emit(&mut self, dt: f32, time: f32, mut spawn: impl FnMut(Vertex, Vector2<f32>)) {
+        if !self.enabled || self.is_finished() || !self.schedule.is_on(time) {
+            self.pending = 0.0;
+            return;
+        }
+        let remaining = self
+            .max_count
+            .map_or(usize::MAX, |max_count| max_count - self.emitted);
+        let due = self.pending + self.rate * dt;
+        // Saturates for an infinite rate, then `MAX_PER_UPDATE` bounds it.
+        let count = (due as usize).min(remaining).min(Self::MAX_PER_UPDATE);
+        self.pending = if count == remaining || count == Self::MAX_PER_UPDATE {
+            0.0
+        } else {
+            due - count as f32
+        };
+
+        for _ in 0..count {
+            let position = self.shape.sample(&mut self.rng);
+            let turn = if self.spread > 0.0 {
+                self.rng.gen_range(-self.spread..=self.spread)
+            } else {
+                0.0
+            };
+            let velocity = Basis2::from_angle(Rad(turn)).rotate_vector(self.velocity);
+            let color = match self.color {
+                ColorSource::Template => self.template.color,
+                ColorSource::Fixed(color) => color,
+                ColorSource::Cycle => self.colors.next_color(),
+            };
+            spawn(
+                Vertex {
+                    position,
+                    previous_position: position,
+                    color,
+                    ..self.template.clone()
+                },
+                velocity,
+            );
+        }
+        self.emitted += count;
+    }
+}
@@ -1,86 +1,295 @@
-use cgmath::num_traits::ToPrimitive;
-use cgmath::ortho;
-use rand::{Rng, thread_rng};
+use std::time::Instant;
 
+use cgmath::Vector2;
+
+use crate::collider::{Collider, Shape};
 use crate::colorgen::ColorGenerator;
-use crate::grid_renderer::GridRenderer;
-use crate::particles_renderer::ParticlesRenderer;
-use crate::resource_manager::ResourceManager;
+use crate::config::SimulationConfig;
+use crate::container::Container;
+use crate::emitter::{ColorSource, Emitter, EmitterId, EmitterShape};
+use crate::force_field::{Falloff, ForceField};
+use crate::gravity::{NBody, PointGravity};
+use crate::link::{self, LinkParams};
+use crate::particle_id::ParticleId;
 use crate::solver::Solver;
-use crate::vertex::Vertex;
-use crate::WORLD_SIZE;
+use crate::vertex::{Motion, Phase, Vertex};
 
-pub struct Engine<'a> {
-    pub resource_manager: &'a ResourceManager,
+pub struct Engine {
     color_generator: ColorGenerator,
     pub solver: Solver,
-    grid_renderer: GridRenderer<'a>,
-    particles_renderer: ParticlesRenderer<'a>,
-    add_objects: bool,
+    /// Emitter toggled by [`Engine::toggle_add_objects`].
+    stream: EmitterId,
+    accumulator: f32,
+    last_tick: Option<Instant>,
+    /// Positions before the last fixed step, indexed by particle slot.
+    previous_positions: Vec<Option<(ParticleId, Vector2<f32>)>>,
 }
 
-impl<'a> Engine<'a> {
-    pub fn new(resource_manager: &'a ResourceManager) -> Self {
+impl Engine {
+    pub fn new(config: SimulationConfig) -> Self {
         let objects = Vec::with_capacity(1000);
-        let solver = Solver::new(objects);
+        let mut solver = Solver::new(config, objects);
         let color_generator = ColorGenerator::new();
+        let mut emitter = Self::emitter(
+            &solver,
+            EmitterShape::Line {
+                a: cgmath::vec2(251.0, 250.0),
+                b: cgmath::vec2(270.0, 268.0),
+            },
+            600.0,
+        )
+        .with_velocity(cgmath::vec2(10.0, 0.0))
+        .with_spread(0.2);
+        emitter.enabled = false;
+        let stream = solver.add_emitter(emitter);
 
-        let grid_renderer = GridRenderer::new(resource_manager);
-        let particles_renderer = ParticlesRenderer::new(resource_manager);
         Self {
-            resource_manager,
             color_generator,
             solver,
-            grid_renderer,
-            particles_renderer,
-            add_objects: false,
+            stream,
+            accumulator: 0.0,
+            last_tick: None,
+            previous_positions: Vec::new(),
         }
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.solver.update(delta_time);
+    /// Advances the simulation by the wall-clock time elapsed since the previous tick.
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = match self.last_tick {
+            Some(last_tick) => now.duration_since(last_tick).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_tick = Some(now);
+        self.advance(elapsed)
+    }
 
-        if self.add_objects {
-            for i in 0..10 {
-                let mut vx = Vertex::new(
-                    cgmath::vec2(
-                        250.0 + i as f32 * 1.0 + thread_rng().gen_range(1.0..20.0) as f32,
-                        250.0 + i as f32 * 2.0,
-                    ),
-                    self.color_generator.next_color(),
-                );
-                vx.accelerate(cgmath::vec2(10.0, 0.0));
-                self.solver.add(vx);
+    /// Runs as many fixed steps as fit into the accumulated time and returns their count.
+    ///
+    /// At most `max_steps_per_frame` steps are run, the rest of the backlog is dropped
+    /// so a slow machine falls behind real time instead of spiralling.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        let time_step = self.solver.config().time_step;
+        let max_steps = self.solver.config().max_steps_per_frame;
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= time_step {
+            if steps == max_steps {
+                self.accumulator %= time_step;
+                break;
             }
+            self.update(time_step);
+            self.accumulator -= time_step;
+            steps += 1;
         }
+        steps
+    }
+
+    /// Fraction of a fixed step accumulated since the last update, used to blend positions.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.solver.config().time_step
     }
 
+    /// Positions blended by [`Engine::alpha`] between the last two fixed steps.
+    pub fn interpolated_positions(&self) -> impl Iterator<Item = Vector2<f32>> + '_ {
+        let alpha = self.alpha();
+        self.solver
+            .get_objects()
+            .iter()
+            .zip(self.solver.ids())
+            .map(
+                move |(object, id)| match self.previous_positions.get(id.slot()) {
+                    Some(&Some((previous_id, previous))) if previous_id == *id => {
+                        previous + (object.position - previous) * alpha
+                    }
+                    _ => object.position,
+                },
+            )
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.previous_positions.clear();
+        for (object, &id) in self.solver.get_objects().iter().zip(self.solver.ids()) {
+            if id.slot() >= self.previous_positions.len() {
+                self.previous_positions.resize(id.slot() + 1, None);
+            }
+            self.previous_positions[id.slot()] = Some((id, object.position));
+        }
+        self.solver.update(delta_time);
+    }
+
+    /// Starts or stops the particle stream.
     pub fn toggle_add_objects(&mut self) {
-        self.add_objects = !self.add_objects;
+        if let Some(stream) = self.solver.emitter_mut(self.stream) {
+            stream.enabled = !stream.enabled;
+        }
     }
 
+    /// Drops a block of 100 particles centered on `(x, y)` on the next update.
     pub fn add_at_position(&mut self, x: f32, y: f32) {
-        for i in (0..10) {
-            let i = i.to_f32().unwrap();
-            for j in (0..10) {
-                let j = j.to_f32().unwrap();
-                let vx = Vertex::new(
-                    cgmath::vec2(x - 5.0 + i, y + 5.0 - j),
-                    self.color_generator.next_color(),
-                );
-                self.solver.add(vx);
-            }
+        let burst = Self::emitter(
+            &self.solver,
+            EmitterShape::Area {
+                min: cgmath::vec2(x - 5.0, y - 5.0),
+                max: cgmath::vec2(x + 5.0, y + 5.0),
+            },
+            f32::INFINITY,
+        )
+        .with_max_count(100);
+        self.solver.add_emitter(burst);
+    }
+
+    /// Pours a block of 100 frictionless water particles centered on `(x, y)`.
+    pub fn add_water(&mut self, x: f32, y: f32) {
+        let radius = self.solver.config().radius;
+        let water = Vertex::new(cgmath::vec2(0.0, 0.0), cgmath::vec3(0.2, 0.4, 1.0))
+            .with_radius(radius)
+            .with_friction(0.0)
+            .with_phase(Phase::Fluid);
+        let burst = Emitter::burst(
+            EmitterShape::Area {
+                min: cgmath::vec2(x - 5.0, y - 5.0),
+                max: cgmath::vec2(x + 5.0, y + 5.0),
+            },
+            100,
+        )
+        .with_template(water);
+        self.solver.add_emitter(burst);
+    }
+
+    /// Drops a lump of 100 mud particles centered on `(x, y)`, which stick together and to
+    /// the walls.
+    pub fn add_mud(&mut self, x: f32, y: f32) {
+        let radius = self.solver.config().radius;
+        if self.solver.config().cohesion_range == 0.0 {
+            self.solver.set_cohesion_range(0.5 * radius);
         }
+        let mud = Vertex::new(cgmath::vec2(0.0, 0.0), cgmath::vec3(0.45, 0.3, 0.15))
+            .with_radius(radius)
+            .with_friction(0.5)
+            .with_cohesion(3000.0)
+            .with_adhesion(2000.0);
+        let burst = Emitter::burst(
+            EmitterShape::Area {
+                min: cgmath::vec2(x - 5.0, y - 5.0),
+                max: cgmath::vec2(x + 5.0, y + 5.0),
+            },
+            100,
+        )
+        .with_template(mud);
+        self.solver.add_emitter(burst);
     }
 
-    pub fn change_gravity(&mut self, x: f32, y: f32) {
-        self.solver.change_gravity(x, y);
+    /// Emitter of rainbow particles with the configured radius.
+    fn emitter(solver: &Solver, shape: EmitterShape, rate: f32) -> Emitter {
+        let radius = solver.config().radius;
+        let template =
+            Vertex::new(cgmath::vec2(0.0, 0.0), cgmath::vec3(1.0, 1.0, 1.0)).with_radius(radius);
+        Emitter::new(shape, rate)
+            .with_template(template)
+            .with_color(ColorSource::Cycle)
+    }
+
+    /// Hangs a cloth from `(x, y)`, pinned at every fifth particle of its top row.
+    pub fn add_cloth(&mut self, x: f32, y: f32) {
+        let radius = self.solver.config().radius;
+        let template =
+            Vertex::new(cgmath::vec2(x, y), self.color_generator.next_color()).with_radius(radius);
+        let columns = 30;
+        let ids = link::cloth(
+            &mut self.solver,
+            cgmath::vec2(x, y),
+            columns,
+            20,
+            2.0 * radius,
+            &template,
+            LinkParams::default(),
+        );
+        for &id in ids[..columns].iter().step_by(5) {
+            self.solver.set_motion(id, Motion::Pinned);
+        }
+    }
+
+    /// Builds a funnel with its outlet at `(x, y)` and a row of pegs below it.
+    pub fn add_funnel(&mut self, x: f32, y: f32) {
+        let radius = self.solver.config().radius;
+        let gap = 4.0 * radius;
+        let center = cgmath::vec2(x, y);
+        self.solver.add_collider(Collider::segment(
+            center + cgmath::vec2(-gap, 0.0),
+            center + cgmath::vec2(-60.0, 40.0),
+        ));
+        self.solver.add_collider(Collider::segment(
+            center + cgmath::vec2(gap, 0.0),
+            center + cgmath::vec2(60.0, 40.0),
+        ));
+        for i in -2..=2 {
+            let peg = center + cgmath::vec2(i as f32 * 15.0, -30.0);
+            self.solver.add_collider(Collider::circle(peg, 3.0));
+        }
+    }
+
+    /// Cycles the container through the walls, a circle and a spinning box.
+    pub fn next_container(&mut self) {
+        let world_size = self.solver.config().world_size;
+        let center = world_size / 2.0;
+        let size = 0.45 * world_size.x.min(world_size.y);
+        let container = match self.solver.container() {
+            Container::Walls => Container::Circle {
+                center,
+                radius: size,
+            },
+            Container::Circle { .. } => Container::RotatingBox {
+                center,
+                half_extents: cgmath::vec2(size, size) / 2f32.sqrt(),
+                angle: 0.0,
+                angular_velocity: 0.5,
+            },
+            _ => Container::Walls,
+        };
+        self.solver.set_container(container);
+    }
+
+    /// Places a drain at `(x, y)` that removes every particle falling into it.
+    pub fn add_drain(&mut self, x: f32, y: f32) {
+        self.solver.add_kill_zone(Shape::Circle {
+            center: cgmath::vec2(x, y),
+            radius: 10.0,
+        });
+    }
+
+    /// Stirs the particles around `(x, y)` with a vortex that fades towards its edge.
+    pub fn add_vortex(&mut self, x: f32, y: f32) {
+        self.solver.add_force_field(ForceField::vortex(
+            cgmath::vec2(x, y),
+            3000.0,
+            100.0,
+            Falloff::Linear,
+        ));
+    }
+
+    /// Switches between the uniform gravity and a planet in the middle of the world.
+    pub fn toggle_planet(&mut self) {
+        let planet = match self.solver.config().point_gravity {
+            Some(_) => None,
+            None => {
+                let center = self.solver.config().world_size / 2.0;
+                Some(PointGravity::new(center, 5.0e6))
+            }
+        };
+        self.solver.set_point_gravity(planet);
+    }
+
+    /// Makes the particles attract each other, or stops it.
+    pub fn toggle_n_body(&mut self) {
+        let n_body = match self.solver.config().n_body {
+            Some(_) => None,
+            None => Some(NBody::new(5000.0)),
+        };
+        self.solver.set_n_body(n_body);
     }
 
-    pub fn render(&self) {
-        let projection = ortho(0.0, WORLD_SIZE.x, 0.0, WORLD_SIZE.y, -1.0, 1.0);
-        // self.grid_renderer.render(projection);
-        self.particles_renderer
-            .render(projection, self.solver.get_objects());
+    pub fn change_gravity(&mut self, x: f32, y: f32) {
+        self.solver.change_gravity(x, y);
     }
 }
@@ -0,0 +1,367 @@
+use std::f32::consts::PI;
+
+use cgmath::{vec2, InnerSpace, Vector2};
+use rayon::prelude::*;
+
+use crate::broadphase::Broadphase;
+use crate::config::SimulationConfig;
+use crate::vertex::{Phase, Vertex};
+
+/// Position-based fluid parameters, used by particles in [`Phase::Fluid`].
+///
+/// Fluid particles still collide like grains, on top of that every sub-step moves them
+/// so that the density around each one matches `rest_density`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fluid {
+    /// Distance up to which neighbours count towards the density.
+    pub smoothing_radius: f32,
+    /// Density the fluid is pushed towards, see [`Fluid::packed_density`].
+    pub rest_density: f32,
+    /// Density corrections per sub-step.
+    pub iterations: u32,
+    /// Share of the velocity difference to its neighbours a particle loses each sub-step.
+    pub viscosity: f32,
+    /// Pulls particles below the rest density towards the center of their neighbours,
+    /// as a share of the way per sub-step scaled by the deficit. Keeps drops and the
+    /// surface together, `0` lets the fluid spread freely.
+    pub surface_tension: f32,
+}
+
+impl Fluid {
+    /// Parameters for fluid particles of `radius` with a kernel reaching two particles out.
+    ///
+    /// The fluid rests a little further apart than its particles touch. The density solve
+    /// only pushes apart, so at contact spacing it would leave the weight to the contacts
+    /// and the fluid would pile up like sand.
+    pub fn new(radius: f32) -> Self {
+        let smoothing_radius = 4.0 * radius;
+        Self {
+            smoothing_radius,
+            rest_density: Self::packed_density(2.2 * radius, smoothing_radius),
+            iterations: 2,
+            viscosity: 0.05,
+            surface_tension: 0.0,
+        }
+    }
+
+    /// Density of unit masses packed hexagonally `spacing` apart.
+    pub fn packed_density(spacing: f32, smoothing_radius: f32) -> f32 {
+        let kernel = Kernel::new(smoothing_radius);
+        let reach = (smoothing_radius / spacing).ceil() as i32 + 1;
+        let mut density = 0.0;
+        for row in -reach..=reach {
+            for column in -reach..=reach {
+                let offset = vec2(
+                    spacing * (column as f32 + 0.5 * row as f32),
+                    spacing * row as f32 * 3f32.sqrt() / 2.0,
+                );
+                density += kernel.density(offset.magnitude2());
+            }
+        }
+        density
+    }
+}
+
+/// 2D smoothing kernels: poly6 for densities and spiky for gradients.
+#[derive(Clone, Copy)]
+struct Kernel {
+    h: f32,
+    h2: f32,
+    poly6: f32,
+    spiky: f32,
+}
+
+impl Kernel {
+    fn new(h: f32) -> Self {
+        Self {
+            h,
+            h2: h * h,
+            poly6: 4.0 / (PI * h.powi(8)),
+            spiky: -30.0 / (PI * h.powi(5)),
+        }
+    }
+
+    fn density(&self, distance2: f32) -> f32 {
+        if distance2 >= self.h2 {
+            return 0.0;
+        }
+        let d = self.h2 - distance2;
+        self.poly6 * d * d * d
+    }
+
+    /// Gradient with respect to the particle `offset` away from its neighbour.
+    fn gradient(&self, offset: Vector2<f32>) -> Vector2<f32> {
+        let distance = offset.magnitude();
+        if distance >= self.h || distance <= f32::EPSILON {
+            return vec2(0.0, 0.0);
+        }
+        let d = self.h - distance;
+        offset * (self.spiky * d * d / distance)
+    }
+}
+
+/// Neighbour search and scratch buffers of the fluid solve, kept between sub-steps.
+#[derive(Default)]
+pub(crate) struct FluidSolver {
+    /// Object index of every fluid particle.
+    indices: Vec<usize>,
+    /// Fluid particles grown to half the smoothing radius, so the broadphase pairs
+    /// everything within the smoothing radius.
+    proxies: Vec<Vertex>,
+    broadphase: Option<(f32, Box<dyn Broadphase>)>,
+    pairs: Vec<(usize, usize)>,
+    /// Neighbours of fluid particle `i` are `neighbours[start[i]..start[i + 1]]`.
+    start: Vec<usize>,
+    neighbours: Vec<usize>,
+    positions: Vec<Vector2<f32>>,
+    densities: Vec<f32>,
+    lambdas: Vec<f32>,
+    corrections: Vec<Vector2<f32>>,
+}
+
+impl FluidSolver {
+    /// Keeps the constraint solvable for particles without close neighbours.
+    const RELAXATION: f32 = 0.1;
+
+    pub fn solve(&mut self, objects: &mut [Vertex], config: &SimulationConfig, dt: f32) {
+        self.indices.clear();
+        self.indices.extend(
+            objects
+                .iter()
+                .enumerate()
+                .filter(|(_, object)| object.phase == Phase::Fluid)
+                .map(|(index, _)| index),
+        );
+        if self.indices.is_empty() {
+            return;
+        }
+        let fluid = config.fluid;
+        self.find_neighbours(objects, config);
+
+        let kernel = Kernel::new(fluid.smoothing_radius);
+        // Rough scale of `|grad C|^2`, keeps the relaxation independent of units.
+        let gradient_scale = kernel.spiky * fluid.smoothing_radius / fluid.rest_density;
+        let relaxation = Self::RELAXATION * gradient_scale * gradient_scale;
+        for _ in 0..fluid.iterations {
+            self.update_positions(objects);
+            self.densities(objects, config, kernel);
+            self.lambdas(objects, config, kernel, relaxation);
+            self.corrections(objects, config, kernel);
+            for (&index, &correction) in self.indices.iter().zip(self.corrections.iter()) {
+                if objects[index].inverse_mass() > 0.0 {
+                    objects[index].position += correction;
+                }
+            }
+        }
+        if fluid.surface_tension > 0.0 {
+            self.apply_surface_tension(objects, config, kernel);
+        }
+        if fluid.viscosity > 0.0 {
+            self.apply_viscosity(objects, config, kernel, dt);
+        }
+    }
+
+    fn find_neighbours(&mut self, objects: &[Vertex], config: &SimulationConfig) {
+        let smoothing_radius = config.fluid.smoothing_radius;
+        let stale = !matches!(self.broadphase, Some((radius, _)) if radius == smoothing_radius);
+        if stale {
+            let proxy_config = SimulationConfig {
+                cell_width: smoothing_radius.max(config.cell_width),
+                ..config.clone()
+            };
+            let broadphase = config.broadphase.create(&proxy_config);
+            self.broadphase = Some((smoothing_radius, broadphase));
+        }
+        self.proxies.clear();
+        self.proxies.extend(self.indices.iter().map(|&index| {
+            Vertex::new(objects[index].position, objects[index].color)
+                .with_radius(smoothing_radius / 2.0)
+        }));
+        let (_, broadphase) = self.broadphase.as_mut().unwrap();
+        broadphase.build(&self.proxies);
+        broadphase.pairs(&mut self.pairs);
+
+        // Counting sort of the pairs into a neighbour list per particle, both ways.
+        let count = self.indices.len();
+        self.start.clear();
+        self.start.resize(count + 1, 0);
+        for &(a, b) in self.pairs.iter() {
+            self.start[a + 1] += 1;
+            self.start[b + 1] += 1;
+        }
+        for index in 0..count {
+            self.start[index + 1] += self.start[index];
+        }
+        self.neighbours.resize(self.start[count], 0);
+        let mut cursor = self.start.clone();
+        for &(a, b) in self.pairs.iter() {
+            self.neighbours[cursor[a]] = b;
+            cursor[a] += 1;
+            self.neighbours[cursor[b]] = a;
+            cursor[b] += 1;
+        }
+    }
+
+    fn update_positions(&mut self, objects: &[Vertex]) {
+        self.positions.clear();
+        self.positions
+            .extend(self.indices.iter().map(|&index| objects[index].position));
+    }
+
+    fn neighbours(&self, particle: usize) -> &[usize] {
+        &self.neighbours[self.start[particle]..self.start[particle + 1]]
+    }
+
+    /// Offset of fluid particle `a` from `b`, the short way across periodic axes.
+    fn offset(&self, config: &SimulationConfig, a: usize, b: usize) -> Vector2<f32> {
+        config.minimum_image(self.positions[a] - self.positions[b])
+    }
+
+    fn densities(&mut self, objects: &[Vertex], config: &SimulationConfig, kernel: Kernel) {
+        let this = &*self;
+        let densities = (0..self.indices.len())
+            .into_par_iter()
+            .map(|particle| {
+                let own = objects[this.indices[particle]].mass * kernel.density(0.0);
+                this.neighbours(particle)
+                    .iter()
+                    .map(|&other| {
+                        let offset = this.offset(config, particle, other);
+                        objects[this.indices[other]].mass * kernel.density(offset.magnitude2())
+                    })
+                    .sum::<f32>()
+                    + own
+            })
+            .collect();
+        self.densities = densities;
+    }
+
+    fn lambdas(
+        &mut self,
+        objects: &[Vertex],
+        config: &SimulationConfig,
+        kernel: Kernel,
+        relaxation: f32,
+    ) {
+        let fluid = config.fluid;
+        let this = &*self;
+        let lambdas = (0..self.indices.len())
+            .into_par_iter()
+            .map(|particle| {
+                // Only pushes apart, pulling sparse particles together is left to the
+                // surface tension.
+                let constraint = (this.densities[particle] / fluid.rest_density - 1.0).max(0.0);
+                let mut own_gradient = vec2(0.0, 0.0);
+                let mut gradients2 = 0.0;
+                for &other in this.neighbours(particle) {
+                    let offset = this.offset(config, particle, other);
+                    let gradient = kernel.gradient(offset)
+                        * (objects[this.indices[other]].mass / fluid.rest_density);
+                    own_gradient += gradient;
+                    gradients2 += gradient.magnitude2();
+                }
+                -constraint / (own_gradient.magnitude2() + gradients2 + relaxation)
+            })
+            .collect();
+        self.lambdas = lambdas;
+    }
+
+    fn corrections(&mut self, objects: &[Vertex], config: &SimulationConfig, kernel: Kernel) {
+        let rest_density = config.fluid.rest_density;
+        let this = &*self;
+        let corrections = (0..self.indices.len())
+            .into_par_iter()
+            .map(|particle| {
+                this.neighbours(particle)
+                    .iter()
+                    .map(|&other| {
+                        let offset = this.offset(config, particle, other);
+                        let mass = objects[this.indices[other]].mass;
+                        kernel.gradient(offset)
+                            * (mass / rest_density * (this.lambdas[particle] + this.lambdas[other]))
+                    })
+                    .fold(vec2(0.0, 0.0), |sum, correction| sum + correction)
+            })
+            .collect();
+        self.corrections = corrections;
+    }
+
+    fn apply_surface_tension(
+        &mut self,
+        objects: &mut [Vertex],
+        config: &SimulationConfig,
+        kernel: Kernel,
+    ) {
+        let fluid = config.fluid;
+        self.update_positions(objects);
+        self.densities(objects, config, kernel);
+        let this = &*self;
+        let corrections: Vec<Vector2<f32>> = (0..self.indices.len())
+            .into_par_iter()
+            .map(|particle| {
+                let deficit = 1.0 - this.densities[particle] / fluid.rest_density;
+                if deficit <= 0.0 {
+                    return vec2(0.0, 0.0);
+                }
+                let (offset, weight) = this.neighbours(particle).iter().fold(
+                    (vec2(0.0, 0.0), 0.0),
+                    |(offset, weight), &other| {
+                        let to_other = this.offset(config, other, particle);
+                        let w = objects[this.indices[other]].mass
+                            * kernel.density(to_other.magnitude2());
+                        (offset + to_other * w, weight + w)
+                    },
+                );
+                if weight <= 0.0 {
+                    return vec2(0.0, 0.0);
+                }
+                offset * (fluid.surface_tension.min(1.0) * deficit / weight)
+            })
+            .collect();
+        for (&index, correction) in self.indices.iter().zip(corrections) {
+            if objects[index].inverse_mass() > 0.0 {
+                objects[index].position += correction;
+            }
+        }
+    }
+
+    /// Blends every velocity towards the average of its neighbours (XSPH).
+    fn apply_viscosity(
+        &mut self,
+        objects: &mut [Vertex],
+        config: &SimulationConfig,
+        kernel: Kernel,
+        dt: f32,
+    ) {
+        let viscosity = config.fluid.viscosity;
+        self.update_positions(objects);
+        self.densities(objects, config, kernel);
+        let velocities: Vec<Vector2<f32>> = self
+            .indices
+            .iter()
+            .map(|&index| objects[index].velocity(dt))
+            .collect();
+        let this = &*self;
+        let changes: Vec<Vector2<f32>> = (0..self.indices.len())
+            .into_par_iter()
+            .map(|particle| {
+                this.neighbours(particle)
+                    .iter()
+                    .map(|&other| {
+                        let offset = this.offset(config, particle, other);
+                        let weight = objects[this.indices[other]].mass / this.densities[other]
+                            * kernel.density(offset.magnitude2());
+                        (velocities[other] - velocities[particle]) * weight
+                    })
+                    .fold(vec2(0.0, 0.0), |sum, change| sum + change)
+                    * viscosity
+            })
+            .collect();
+        for (&index, change) in self.indices.iter().zip(changes) {
+            let object = &mut objects[index];
+            if object.inverse_mass() > 0.0 {
+                object.previous_position -= change * dt;
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use cgmath::{vec2, InnerSpace, Vector2};
+
+use crate::config::SimulationConfig;
+use crate::vertex::Vertex;
+
+/// Acceleration of a particle at simulation time, for [`ForceField::Custom`].
+pub type FieldFn = Box<dyn Fn(&Vertex, f32) -> Vector2<f32> + Send + Sync>;
+
+/// How the strength of a round field changes with the distance to its center.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Falloff {
+    /// Full strength everywhere within the radius.
+    #[default]
+    None,
+    /// Fades linearly to zero at the radius.
+    Linear,
+    /// Full strength up to `core`, then drops with the inverse square of the distance.
+    InverseSquare { core: f32 },
+}
+
+impl Falloff {
+    fn factor(&self, distance: f32, radius: f32) -> f32 {
+        match *self {
+            Falloff::None => 1.0,
+            Falloff::Linear => 1.0 - distance / radius,
+            Falloff::InverseSquare { core } => {
+                let ratio = core / distance.max(core);
+                ratio * ratio
+            }
+        }
+    }
+}
+
+/// Acceleration applied to every dynamic particle each sub-step, on top of gravity.
+///
+/// Like gravity, fields accelerate light and heavy particles alike. Round fields measure
+/// distances across periodic axes the short way.
+pub enum ForceField {
+    /// Pulls towards `center` within `radius`, a negative `strength` pushes away.
+    Point {
+        center: Vector2<f32>,
+        strength: f32,
+        radius: f32,
+        falloff: Falloff,
+    },
+    /// Swirls counter-clockwise around `center` within `radius`, clockwise for a negative
+    /// `strength`.
+    Vortex {
+        center: Vector2<f32>,
+        strength: f32,
+        radius: f32,
+        falloff: Falloff,
+    },
+    /// Constant acceleration inside the box from `min` to `max`.
+    Wind {
+        min: Vector2<f32>,
+        max: Vector2<f32>,
+        acceleration: Vector2<f32>,
+    },
+    /// Swirling, divergence-free noise with features about `scale` apart that drifts
+    /// with `speed` features per second. Accelerations are in the order of `strength`.
+    Turbulence {
+        strength: f32,
+        scale: f32,
+        speed: f32,
+    },
+    Custom(FieldFn),
+}
+
+impl ForceField {
+    /// Step used to differentiate the turbulence noise, in features.
+    const NOISE_STEP: f32 = 0.01;
+
+    pub fn attractor(center: Vector2<f32>, strength: f32, radius: f32, falloff: Falloff) -> Self {
+        ForceField::Point {
+            center,
+            strength,
+            radius,
+            falloff,
+        }
+    }
+
+    pub fn repulsor(center: Vector2<f32>, strength: f32, radius: f32, falloff: Falloff) -> Self {
+        Self::attractor(center, -strength, radius, falloff)
+    }
+
+    pub fn vortex(center: Vector2<f32>, strength: f32, radius: f32, falloff: Falloff) -> Self {
+        ForceField::Vortex {
+            center,
+            strength,
+            radius,
+            falloff,
+        }
+    }
+
+    pub fn wind(min: Vector2<f32>, max: Vector2<f32>, acceleration: Vector2<f32>) -> Self {
+        ForceField::Wind {
+            min,
+            max,
+            acceleration,
+        }
+    }
+
+    pub fn turbulence(strength: f32, scale: f32, speed: f32) -> Self {
+        ForceField::Turbulence {
+            strength,
+            scale,
+            speed,
+        }
+    }
+
+    pub fn custom(field: impl Fn(&Vertex, f32) -> Vector2<f32> + Send + Sync + 'static) -> Self {
+        ForceField::Custom(Box::new(field))
+    }
+
+    /// Acceleration of `object` at `time`.
+    pub(crate) fn acceleration(
+        &self,
+        object: &Vertex,
+        time: f32,
+        config: &SimulationConfig,
+    ) -> Vector2<f32> {
+        match self {
+            ForceField::Point {
+                center,
+                strength,
+                radius,
+                falloff,
+            } => round_field(*center - object.position, *radius, *falloff, config)
+                .map_or(vec2(0.0, 0.0), |(direction, factor)| {
+                    direction * (strength * factor)
+                }),
+            ForceField::Vortex {
+                center,
+                strength,
+                radius,
+                falloff,
+            } => round_field(object.position - *center, *radius, *falloff, config)
+                .map_or(vec2(0.0, 0.0), |(direction, factor)| {
+                    vec2(-direction.y, direction.x) * (strength * factor)
+                }),
+            ForceField::Wind {
+                min,
+                max,
+                acceleration,
+            } => {
+                let position = object.position;
+                if position.x >= min.x
+                    && position.x <= max.x
+                    && position.y >= min.y
+                    && position.y <= max.y
+                {
+                    *acceleration
+                } else {
+                    vec2(0.0, 0.0)
+                }
+            }
+            ForceField::Turbulence {
+                strength,
+                scale,
+                speed,
+            } => {
+                let p = object.position / *scale;
+                let t = time * speed;
+                let h = Self::NOISE_STEP;
+                let dx = value_noise(p + vec2(h, 0.0), t) - value_noise(p - vec2(h, 0.0), t);
+                let dy = value_noise(p + vec2(0.0, h), t) - value_noise(p - vec2(0.0, h), t);
+                // The curl of the noise, which swirls without sources or sinks.
+                vec2(dy, -dx) * (strength / (2.0 * h))
+            }
+            ForceField::Custom(field) => field(object, time),
+        }
+    }
+}
+
+/// Unit direction of `offset` and the falloff factor, `None` outside of `radius`.
+fn round_field(
+    offset: Vector2<f32>,
+    radius: f32,
+    falloff: Falloff,
+    config: &SimulationConfig,
+) -> Option<(Vector2<f32>, f32)> {
+    let offset = config.minimum_image(offset);
+    let distance = offset.magnitude();
+    if distance >= radius || distance <= f32::EPSILON {
+        return None;
+    }
+    Some((offset / distance, falloff.factor(distance, radius)))
+}
+
+/// Smoothly interpolated random values in `[0, 1]` on an integer lattice over space and time.
+fn value_noise(p: Vector2<f32>, t: f32) -> f32 {
+    let (x, y, z) = (p.x.floor(), p.y.floor(), t.floor());
+    let (i, j, k) = (x as i32, y as i32, z as i32);
+    let smooth = |f: f32| f * f * (3.0 - 2.0 * f);
+    let (u, v, w) = (smooth(p.x - x), smooth(p.y - y), smooth(t - z));
+    let lerp = |a: f32, b: f32, f: f32| a + (b - a) * f;
+    let layer = |k: i32| {
+        let bottom = lerp(lattice(i, j, k), lattice(i + 1, j, k), u);
+        let top = lerp(lattice(i, j + 1, k), lattice(i + 1, j + 1, k), u);
+        lerp(bottom, top, v)
+    };
+    lerp(layer(k), layer(k + 1), w)
+}
+
+fn lattice(i: i32, j: i32, k: i32) -> f32 {
+    let mut hash = (i as u32)
+        .wrapping_mul(0x8da6_b343)
+        .wrapping_add((j as u32).wrapping_mul(0xd816_3841))
+        .wrapping_add((k as u32).wrapping_mul(0xcb1a_b31f));
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
@@ -0,0 +1,255 @@
+use cgmath::{vec2, InnerSpace, Vector2};
+use rayon::prelude::*;
+
+use crate::vertex::Vertex;
+
+/// Gravity towards a fixed center, for planet-like scenes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointGravity {
+    pub center: Vector2<f32>,
+    /// Acceleration at a distance of one unit, it falls off with the inverse square.
+    pub strength: f32,
+    /// Distance below which the pull stops growing, keeps particles at the center sane.
+    pub softening: f32,
+}
+
+impl PointGravity {
+    pub fn new(center: Vector2<f32>, strength: f32) -> Self {
+        Self {
+            center,
+            strength,
+            softening: 1.0,
+        }
+    }
+
+    /// Acceleration of a particle displaced by `offset` from the center.
+    pub(crate) fn acceleration(&self, offset: Vector2<f32>) -> Vector2<f32> {
+        -offset * (self.strength * softened_inverse_cube(offset, self.softening))
+    }
+}
+
+/// Mutual attraction of all particles, `constant * mass / distance^2` towards each other.
+///
+/// Forces are approximated with a Barnes-Hut tree, so a step costs `O(n log n)`. Distances
+/// are measured without wrapping across periodic axes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NBody {
+    pub constant: f32,
+    /// Distance below which the pull between two particles stops growing.
+    pub softening: f32,
+    /// Groups of particles smaller than `theta` times their distance are treated as a single
+    /// mass. `0` sums every pair exactly, `0.5` is a good trade-off.
+    pub theta: f32,
+}
+
+impl NBody {
+    pub fn new(constant: f32) -> Self {
+        Self {
+            constant,
+            softening: 1.0,
+            theta: 0.5,
+        }
+    }
+}
+
+/// `1 / (|offset|^2 + softening^2)^(3/2)`, scales an offset into an inverse-square pull.
+fn softened_inverse_cube(offset: Vector2<f32>, softening: f32) -> f32 {
+    let distance2 = offset.magnitude2() + softening * softening;
+    1.0 / (distance2 * distance2.sqrt())
+}
+
+const NO_CHILDREN: usize = usize::MAX;
+
+struct Node {
+    center: Vector2<f32>,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vector2<f32>,
+    /// Index of the first of four consecutive children in `BarnesHut::nodes`.
+    children: usize,
+    /// Range of `BarnesHut::bodies` inside the node.
+    start: usize,
+    end: usize,
+}
+
+impl Node {
+    fn new(center: Vector2<f32>, half_size: f32, start: usize, end: usize) -> Self {
+        Self {
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: center,
+            children: NO_CHILDREN,
+            start,
+            end,
+        }
+    }
+
+    fn contains(&self, position: Vector2<f32>) -> bool {
+        (position.x - self.center.x).abs() <= self.half_size
+            && (position.y - self.center.y).abs() <= self.half_size
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Body {
+    position: Vector2<f32>,
+    mass: f32,
+    index: usize,
+}
+
+/// Quadtree of particle masses for the N-body forces, rebuilt every sub-step.
+#[derive(Default)]
+pub(crate) struct BarnesHut {
+    nodes: Vec<Node>,
+    bodies: Vec<Body>,
+}
+
+impl BarnesHut {
+    const LEAF_SIZE: usize = 8;
+    const MAX_DEPTH: usize = 16;
+
+    /// Accelerates every dynamic object by the pull of all the others.
+    pub fn apply(&mut self, objects: &mut [Vertex], n_body: NBody) {
+        self.build(objects);
+        if self.nodes.is_empty() {
+            return;
+        }
+        let tree = &*self;
+        objects
+            .par_iter_mut()
+            .enumerate()
+            .filter(|(_, object)| object.inverse_mass() > 0.0)
+            .for_each(|(index, object)| {
+                let acceleration = tree.acceleration(index, object.position, n_body);
+                object.accelerate(acceleration);
+            });
+    }
+
+    fn build(&mut self, objects: &[Vertex]) {
+        self.nodes.clear();
+        self.bodies.clear();
+        self.bodies.extend(
+            objects
+                .iter()
+                .enumerate()
+                .filter(|(_, object)| object.mass > 0.0)
+                .map(|(index, object)| Body {
+                    position: object.position,
+                    mass: object.mass,
+                    index,
+                }),
+        );
+        if self.bodies.is_empty() {
+            return;
+        }
+
+        let mut min = vec2(f32::MAX, f32::MAX);
+        let mut max = vec2(f32::MIN, f32::MIN);
+        for body in self.bodies.iter() {
+            min = vec2(min.x.min(body.position.x), min.y.min(body.position.y));
+            max = vec2(max.x.max(body.position.x), max.y.max(body.position.y));
+        }
+        let half_size = 0.5 * (max.x - min.x).max(max.y - min.y) + f32::EPSILON;
+        self.nodes.push(Node::new(
+            (min + max) / 2.0,
+            half_size,
+            0,
+            self.bodies.len(),
+        ));
+        self.fill(0, 0);
+    }
+
+    /// Sums the mass of a node and splits it into children while it holds too many bodies.
+    fn fill(&mut self, index: usize, depth: usize) {
+        let Node {
+            center,
+            half_size,
+            start,
+            end,
+            ..
+        } = self.nodes[index];
+        if start == end {
+            return;
+        }
+        let bodies = &mut self.bodies[start..end];
+        let mass: f32 = bodies.iter().map(|body| body.mass).sum();
+        let weighted = bodies
+            .iter()
+            .fold(vec2(0.0, 0.0), |sum, body| sum + body.position * body.mass);
+        self.nodes[index].mass = mass;
+        self.nodes[index].center_of_mass = weighted / mass;
+        if end - start <= Self::LEAF_SIZE || depth == Self::MAX_DEPTH {
+            return;
+        }
+
+        // Orders the bodies by quadrant, the same order as the child offsets below.
+        let lower = partition(bodies, |body| body.position.y < center.y);
+        let lower_left = partition(&mut bodies[..lower], |body| body.position.x < center.x);
+        let upper_left = partition(&mut bodies[lower..], |body| body.position.x < center.x);
+        let bounds = [
+            start,
+            start + lower_left,
+            start + lower,
+            start + lower + upper_left,
+            end,
+        ];
+
+        let children = self.nodes.len();
+        self.nodes[index].children = children;
+        let quarter = half_size / 2.0;
+        let offsets = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+        for (quadrant, (x, y)) in offsets.into_iter().enumerate() {
+            self.nodes.push(Node::new(
+                center + vec2(x, y) * quarter,
+                quarter,
+                bounds[quadrant],
+                bounds[quadrant + 1],
+            ));
+        }
+        for child in children..children + 4 {
+            self.fill(child, depth + 1);
+        }
+    }
+
+    fn acceleration(&self, index: usize, position: Vector2<f32>, n_body: NBody) -> Vector2<f32> {
+        let theta2 = n_body.theta * n_body.theta;
+        let mut acceleration = vec2(0.0, 0.0);
+        let mut pull = |mass: f32, offset: Vector2<f32>| {
+            acceleration += offset * (mass * softened_inverse_cube(offset, n_body.softening));
+        };
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.mass == 0.0 {
+                continue;
+            }
+            let offset = node.center_of_mass - position;
+            let size = 2.0 * node.half_size;
+            if node.children == NO_CHILDREN {
+                for body in self.bodies[node.start..node.end].iter() {
+                    if body.index != index {
+                        pull(body.mass, body.position - position);
+                    }
+                }
+            } else if !node.contains(position) && size * size < theta2 * offset.magnitude2() {
+                pull(node.mass, offset);
+            } else {
+                stack.extend(node.children..node.children + 4);
+            }
+        }
+        acceleration * n_body.constant
+    }
+}
+
+/// Moves the elements matching `left` to the front and returns how many there are.
+fn partition<T>(items: &mut [T], left: impl Fn(&T) -> bool) -> usize {
+    let mut count = 0;
+    for index in 0..items.len() {
+        if left(&items[index]) {
+            items.swap(count, index);
+            count += 1;
+        }
+    }
+    count
+}
@@ -1,67 +1,217 @@
-use crate::{CELL_WIDTH, GRID_HEIGHT, GRID_WIDTH};
+use cgmath::Vector2;
+use rayon::prelude::*;
 
-#[derive(Debug)]
-struct Cell {
-    objects: [usize; Cell::MAX_CELL_SIZE],
-    objects_count: usize,
+use crate::broadphase::Broadphase;
+use crate::config::{Periodic, SimulationConfig};
+use crate::vertex::Vertex;
+
+/// The most populated cell found during the last [`Grid::rebuild`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DensestCell {
+    pub column: usize,
+    pub row: usize,
+    pub count: usize,
+}
+
+/// Uniform grid stored as cell ranges over a flat buffer of object ids.
+///
+/// Objects are bucketed with a counting sort: `cell_start[i]..cell_start[i + 1]`
+/// is the range of `cell_objects` that belongs to cell `i`, so there is no
+/// limit on how many objects a single cell can hold.
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cell_size: Vector2<f32>,
+    world_size: Vector2<f32>,
+    periodic: Periodic,
+    cell_start: Vec<usize>,
+    cell_objects: Vec<usize>,
+    object_cells: Vec<usize>,
+    cursor: Vec<usize>,
+    densest_cell: DensestCell,
 }
 
-impl Cell {
-    const MAX_CELL_SIZE: usize = 8;
-    const MAX_CELL_INDEX: usize = Cell::MAX_CELL_SIZE - 1;
+impl Grid {
+    const OUTSIDE: usize = usize::MAX;
 
-    pub fn new() -> Self {
+    pub fn new(config: &SimulationConfig) -> Self {
+        let width = config.grid_width();
+        let height = config.grid_height();
         Self {
-            objects: [0; Cell::MAX_CELL_SIZE],
-            objects_count: 0,
+            width,
+            height,
+            cell_size: config.cell_size(),
+            world_size: config.world_size,
+            periodic: config.periodic,
+            cell_start: vec![0; width * height + 1],
+            cell_objects: Vec::new(),
+            object_cells: Vec::new(),
+            cursor: vec![0; width * height],
+            densest_cell: DensestCell::default(),
         }
     }
 
-    pub fn clear(&mut self) {
-        self.objects_count = 0;
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    pub fn add(&mut self, object: usize) {
-        if self.objects_count == Cell::MAX_CELL_INDEX {
-            return;
-        }
-        self.objects[self.objects_count] = object;
-        self.objects_count += 1;
+    pub fn height(&self) -> usize {
+        self.height
     }
 
-    pub fn get_objects(&self) -> &[usize] {
-        &self.objects[..self.objects_count]
+    pub fn densest_cell(&self) -> DensestCell {
+        self.densest_cell
     }
-}
 
-pub struct Grid {
-    data: [Cell; GRID_WIDTH * GRID_HEIGHT],
-}
+    /// Rebuilds the grid from object positions, the object id is its index in `positions`.
+    pub fn rebuild(&mut self, positions: impl Iterator<Item = Vector2<f32>>) {
+        self.cell_start.fill(0);
+        self.object_cells.clear();
+        for position in positions {
+            let cell = match self.cell_coords(position.x, position.y) {
+                Some((column, row)) => self.cell_index(column, row),
+                None => Self::OUTSIDE,
+            };
+            if cell != Self::OUTSIDE {
+                self.cell_start[cell + 1] += 1;
+            }
+            self.object_cells.push(cell);
+        }
 
-impl Grid {
-    pub fn new() -> Self {
-        let arr: Vec<Cell> = (0..GRID_WIDTH * GRID_HEIGHT).map(|_| Cell::new()).collect();
-        Self {
-            data: arr.try_into().unwrap(),
+        self.densest_cell = DensestCell::default();
+        for cell in 0..self.cursor.len() {
+            let count = self.cell_start[cell + 1];
+            if count > self.densest_cell.count {
+                self.densest_cell = DensestCell {
+                    column: cell % self.width,
+                    row: cell / self.width,
+                    count,
+                };
+            }
+            self.cell_start[cell + 1] += self.cell_start[cell];
+            self.cursor[cell] = self.cell_start[cell];
         }
-    }
 
-    pub fn clear(&mut self) {
-        for cell in self.data.iter_mut() {
-            cell.clear();
+        self.cell_objects
+            .resize(self.cell_start[self.cursor.len()], 0);
+        for (object_id, &cell) in self.object_cells.iter().enumerate() {
+            if cell != Self::OUTSIDE {
+                self.cell_objects[self.cursor[cell]] = object_id;
+                self.cursor[cell] += 1;
+            }
         }
     }
 
-    pub fn add_object(&mut self, x: f32, y: f32, object_id: usize) {
-        let column_index = (x / CELL_WIDTH).floor() as usize;
-        let row_index = (y / CELL_WIDTH).floor() as usize;
-        if column_index >= GRID_WIDTH || row_index >= GRID_HEIGHT {
-            return;
+    /// Returns `(column, row)` of the cell containing `(x, y)`, if it is inside the grid.
+    /// Coordinates on periodic axes are wrapped into the world first.
+    pub fn cell_coords(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let column = Self::axis_cell(
+            x,
+            self.cell_size.x,
+            self.world_size.x,
+            self.width,
+            self.periodic.x,
+        )?;
+        let row = Self::axis_cell(
+            y,
+            self.cell_size.y,
+            self.world_size.y,
+            self.height,
+            self.periodic.y,
+        )?;
+        Some((column, row))
+    }
+
+    pub fn get_cell_objects(&self, column: usize, row: usize) -> &[usize] {
+        let index = self.cell_index(column, row);
+        &self.cell_objects[self.cell_start[index]..self.cell_start[index + 1]]
+    }
+
+    /// Iterates over `(column, row)` of the cell itself and its existing neighbours,
+    /// wrapping around periodic axes.
+    pub fn neighbour_cells(
+        &self,
+        column: usize,
+        row: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let columns = Self::neighbours(column, self.width, self.periodic.x);
+        let rows = Self::neighbours(row, self.height, self.periodic.y);
+        rows.flat_map(move |r| columns.clone().map(move |c| (c, r)))
+    }
+
+    /// Iterates over objects in the cell and its neighbours.
+    pub fn get_neighbour_objects(
+        &self,
+        column: usize,
+        row: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.neighbour_cells(column, row)
+            .flat_map(|(c, r)| self.get_cell_objects(c, r).iter().copied())
+    }
+
+    /// Indices next to `index` on an axis of `count` cells, each visited once.
+    fn neighbours(
+        index: usize,
+        count: usize,
+        periodic: bool,
+    ) -> impl Iterator<Item = usize> + Clone {
+        let (cells, len) = if periodic && count >= 3 {
+            ([(index + count - 1) % count, index, (index + 1) % count], 3)
+        } else if periodic {
+            ([0, 1, 2], count)
+        } else {
+            let first = index.saturating_sub(1);
+            let last = (index + 1).min(count - 1);
+            ([first, first + 1, first + 2], last - first + 1)
+        };
+        cells.into_iter().take(len)
+    }
+
+    fn axis_cell(
+        position: f32,
+        cell_size: f32,
+        world_size: f32,
+        count: usize,
+        periodic: bool,
+    ) -> Option<usize> {
+        if periodic {
+            // Rounding can put a wrapped coordinate exactly on the far edge.
+            let wrapped = position.rem_euclid(world_size);
+            return Some(((wrapped / cell_size) as usize).min(count - 1));
+        }
+        if position < 0.0 {
+            return None;
         }
-        self.data[row_index * GRID_HEIGHT + column_index].add(object_id);
+        let index = (position / cell_size) as usize;
+        (index < count).then_some(index)
+    }
+
+    fn cell_index(&self, column: usize, row: usize) -> usize {
+        row * self.width + column
+    }
+}
+
+impl Broadphase for Grid {
+    fn build(&mut self, objects: &[Vertex]) {
+        self.rebuild(objects.iter().map(|object| object.position));
+    }
+
+    fn pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        pairs.clear();
+        pairs.par_extend(
+            self.object_cells
+                .par_iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell != Self::OUTSIDE)
+                .flat_map_iter(|(i, &cell)| {
+                    self.get_neighbour_objects(cell % self.width, cell / self.width)
+                        .filter(move |&j| j > i)
+                        .map(move |j| (i, j))
+                }),
+        );
     }
 
-    pub fn get_cell_objects(&self, row_index: usize, column_index: usize) -> &[usize] {
-        self.data[column_index * GRID_HEIGHT + row_index].get_objects()
+    fn densest_cell(&self) -> DensestCell {
+        self.densest_cell
     }
 }
@@ -1,9 +1,11 @@
 use std::mem::size_of;
+use std::ptr;
 
 use cgmath::{Matrix4, Vector2};
 use gl::types::{GLint, GLsizeiptr, GLuint};
 
-use crate::{CELL_WIDTH, GRID_HEIGHT, GRID_WIDTH};
+use physics_engine::config::SimulationConfig;
+
 use crate::resource_manager::ResourceManager;
 
 pub struct GridRenderer<'a> {
@@ -13,24 +15,23 @@ pub struct GridRenderer<'a> {
 }
 
 impl<'a> GridRenderer<'a> {
-    pub fn new(resource_manager: &'a ResourceManager) -> Self {
+    pub fn new(resource_manager: &'a ResourceManager, config: &SimulationConfig) -> Self {
         let mut renderer = Self {
             resource_manager,
             vao: 0,
             cells: vec![],
         };
-        renderer.init_vao();
+        renderer.init_vao(config);
         renderer
     }
 
-    fn init_vao(&mut self) {
+    fn init_vao(&mut self, config: &SimulationConfig) {
         let mut vbo: GLuint = 0;
-        for x in (0..=GRID_HEIGHT) {
-            for y in 0..=GRID_WIDTH {
-                self.cells.push(cgmath::vec2(
-                    x as f32 * CELL_WIDTH as f32,
-                    y as f32 * CELL_WIDTH as f32,
-                ));
+        let cell_size = config.cell_size();
+        for x in 0..=config.grid_width() {
+            for y in 0..=config.grid_height() {
+                self.cells
+                    .push(cgmath::vec2(x as f32 * cell_size.x, y as f32 * cell_size.y));
             }
         }
 
@@ -51,7 +52,7 @@ impl<'a> GridRenderer<'a> {
                 gl::FLOAT,
                 gl::FALSE,
                 size_of::<Vector2<f32>>() as GLint,
-                0 as *const _,
+                ptr::null(),
             );
             gl::EnableVertexAttribArray(0);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
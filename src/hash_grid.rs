@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use cgmath::Vector2;
+use rayon::prelude::*;
+
+use crate::broadphase::Broadphase;
+use crate::vertex::Vertex;
+
+/// Uniform grid that only stores occupied cells, keyed by cell coordinates.
+///
+/// Unlike [`crate::Grid`] it has no extents, so it works for open worlds and negative
+/// coordinates, at the cost of a hash lookup per cell.
+pub struct HashGrid {
+    cell_width: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    object_cells: Vec<(i32, i32)>,
+}
+
+impl HashGrid {
+    pub fn new(cell_width: f32) -> Self {
+        Self {
+            cell_width,
+            cells: HashMap::new(),
+            object_cells: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the grid from object positions, the object id is its index in `positions`.
+    pub fn rebuild(&mut self, positions: impl Iterator<Item = Vector2<f32>>) {
+        // Keep the buffers of cells that stay occupied between frames.
+        self.cells.values_mut().for_each(Vec::clear);
+        self.object_cells.clear();
+        for (object_id, position) in positions.enumerate() {
+            let cell = self.cell_coords(position);
+            self.cells.entry(cell).or_default().push(object_id);
+            self.object_cells.push(cell);
+        }
+        self.cells.retain(|_, objects| !objects.is_empty());
+    }
+
+    pub fn cell_coords(&self, position: Vector2<f32>) -> (i32, i32) {
+        (
+            (position.x / self.cell_width).floor() as i32,
+            (position.y / self.cell_width).floor() as i32,
+        )
+    }
+
+    pub fn get_cell_objects(&self, cell: (i32, i32)) -> &[usize] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates over objects in the cell and its neighbours.
+    pub fn get_neighbour_objects(&self, (x, y): (i32, i32)) -> impl Iterator<Item = usize> + '_ {
+        (y - 1..=y + 1)
+            .flat_map(move |row| (x - 1..=x + 1).map(move |column| (column, row)))
+            .flat_map(|cell| self.get_cell_objects(cell).iter().copied())
+    }
+}
+
+impl Broadphase for HashGrid {
+    fn build(&mut self, objects: &[Vertex]) {
+        self.rebuild(objects.iter().map(|object| object.position));
+    }
+
+    fn pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        pairs.clear();
+        pairs.par_extend(
+            self.object_cells
+                .par_iter()
+                .enumerate()
+                .flat_map_iter(|(i, &cell)| {
+                    self.get_neighbour_objects(cell)
+                        .filter(move |&j| j > i)
+                        .map(move |j| (i, j))
+                }),
+        );
+    }
+}
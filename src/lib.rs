@@ -0,0 +1,36 @@
+pub mod broadphase;
+pub mod collider;
+pub mod colorgen;
+pub mod config;
+pub mod container;
+pub mod emitter;
+pub mod engine;
+pub mod fluid;
+pub mod force_field;
+pub mod gravity;
+pub mod grid;
+pub mod hash_grid;
+pub mod link;
+pub mod particle_id;
+pub mod quadtree;
+pub mod solver;
+pub mod sweep_and_prune;
+pub mod vertex;
+
+pub use broadphase::{Broadphase, BroadphaseKind};
+pub use collider::{Collider, ColliderSet, Shape};
+pub use config::{Periodic, SimulationConfig, Walls};
+pub use container::{Container, Sdf};
+pub use emitter::{ColorSource, Emitter, EmitterId, EmitterShape, Schedule};
+pub use engine::Engine;
+pub use fluid::Fluid;
+pub use force_field::{Falloff, FieldFn, ForceField};
+pub use gravity::{NBody, PointGravity};
+pub use grid::{DensestCell, Grid};
+pub use hash_grid::HashGrid;
+pub use link::{Link, LinkParams};
+pub use particle_id::ParticleId;
+pub use quadtree::LooseQuadtree;
+pub use solver::{Path, Solver};
+pub use sweep_and_prune::SweepAndPrune;
+pub use vertex::{Integrator, Motion, Phase, Vertex};
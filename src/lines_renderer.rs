@@ -0,0 +1,164 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::mem::size_of;
+use std::ptr;
+
+use cgmath::{Matrix4, Vector2};
+use gl::types::{GLint, GLsizeiptr, GLuint};
+
+use physics_engine::{Collider, Container, Shape, Solver};
+
+use crate::resource_manager::ResourceManager;
+
+/// Draws links and the static shapes of the level (colliders, kill zones, containers) as
+/// white lines.
+pub struct LinesRenderer<'a> {
+    resource_manager: &'a ResourceManager,
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl<'a> LinesRenderer<'a> {
+    pub fn new(resource_manager: &'a ResourceManager) -> Self {
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::VertexAttribPointer(
+                0,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                size_of::<Vector2<f32>>() as GLint,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        Self {
+            resource_manager,
+            vao,
+            vbo,
+        }
+    }
+
+    /// Draws every link of the solver as a line between `positions` of its ends, given in
+    /// storage order.
+    pub fn render_links(
+        &self,
+        projection: Matrix4<f32>,
+        solver: &Solver,
+        positions: &[Vector2<f32>],
+    ) {
+        let lines = solver
+            .get_links()
+            .iter()
+            .filter_map(|link| {
+                let a = solver.index_of(link.a)?;
+                let b = solver.index_of(link.b)?;
+                Some([positions[a], positions[b]])
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        self.draw_lines(projection, &lines);
+    }
+
+    /// Draws collider outlines, circles are approximated with a polygon.
+    pub fn render_colliders(&self, projection: Matrix4<f32>, colliders: &[Collider]) {
+        self.render_shapes(projection, colliders.iter().map(|collider| &collider.shape));
+    }
+
+    pub fn render_shapes<'s>(
+        &self,
+        projection: Matrix4<f32>,
+        shapes: impl IntoIterator<Item = &'s Shape>,
+    ) {
+        let mut lines = Vec::new();
+        for shape in shapes {
+            match shape {
+                Shape::Segment { a, b } => lines.extend([*a, *b]),
+                Shape::Circle { center, radius } => {
+                    Self::push_outline(&mut lines, &Self::arc(*center, *radius, 0.0, TAU))
+                }
+                Shape::Polygon { points } => Self::push_outline(&mut lines, points),
+            }
+        }
+        self.draw_lines(projection, &lines);
+    }
+
+    /// Draws the container outline at `time`, user distance functions are not drawn.
+    pub fn render_container(&self, projection: Matrix4<f32>, container: &Container, time: f32) {
+        let mut lines = Vec::new();
+        match container {
+            Container::Circle { center, radius } => {
+                Self::push_outline(&mut lines, &Self::arc(*center, *radius, 0.0, TAU))
+            }
+            Container::Capsule { a, b, radius } => {
+                let edge = *b - *a;
+                let start = edge.y.atan2(edge.x) - FRAC_PI_2;
+                let mut points = Self::arc(*b, *radius, start, PI);
+                points.extend(Self::arc(*a, *radius, start + PI, PI));
+                Self::push_outline(&mut lines, &points);
+            }
+            Container::RotatingBox {
+                center,
+                half_extents,
+                angle,
+                angular_velocity,
+            } => {
+                let (sin, cos) = (angle + angular_velocity * time).sin_cos();
+                let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+                    let (x, y) = (x * half_extents.x, y * half_extents.y);
+                    center + cgmath::vec2(x * cos - y * sin, x * sin + y * cos)
+                });
+                Self::push_outline(&mut lines, &corners);
+            }
+            Container::Walls | Container::Open | Container::Sdf(_) => {}
+        }
+        self.draw_lines(projection, &lines);
+    }
+
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    /// Points along an arc of `sweep` radians, including both ends.
+    fn arc(center: Vector2<f32>, radius: f32, start: f32, sweep: f32) -> Vec<Vector2<f32>> {
+        (0..=Self::CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = start + i as f32 / Self::CIRCLE_SEGMENTS as f32 * sweep;
+                center + cgmath::vec2(angle.cos(), angle.sin()) * radius
+            })
+            .collect()
+    }
+
+    fn push_outline(lines: &mut Vec<Vector2<f32>>, points: &[Vector2<f32>]) {
+        for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+            lines.extend([*a, *b]);
+        }
+    }
+
+    fn draw_lines(&self, projection: Matrix4<f32>, lines: &[Vector2<f32>]) {
+        if lines.is_empty() {
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                lines.len() as isize * size_of::<Vector2<f32>>() as GLsizeiptr,
+                lines.as_ptr().cast(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            // Plain white lines, same as the grid.
+            let cell_shader = self.resource_manager.get_shader("cell");
+            cell_shader.use_shader();
+            cell_shader.set_matrix4(projection, "projection");
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::LINES, 0, lines.len() as i32);
+        }
+    }
+}
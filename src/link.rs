@@ -0,0 +1,179 @@
+use cgmath::{InnerSpace, Vector2};
+
+use crate::config::SimulationConfig;
+use crate::particle_id::ParticleId;
+use crate::solver::Solver;
+use crate::vertex::Vertex;
+
+/// Distance constraint keeping two objects `rest_length` apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Link {
+    pub a: ParticleId,
+    pub b: ParticleId,
+    pub rest_length: f32,
+    /// Fraction of the length error corrected each sub-step, `1` is rigid.
+    pub stiffness: f32,
+    /// The link breaks when stretched beyond `rest_length * break_ratio`.
+    pub break_ratio: Option<f32>,
+}
+
+impl Link {
+    pub fn new(a: ParticleId, b: ParticleId, rest_length: f32) -> Self {
+        LinkParams::default().link(a, b, rest_length)
+    }
+
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    pub fn with_break_ratio(mut self, break_ratio: f32) -> Self {
+        self.break_ratio = Some(break_ratio);
+        self
+    }
+
+    /// Moves both ends, stored at indices `a` and `b`, towards the rest length and returns
+    /// `false` if the link broke.
+    pub(crate) fn solve(
+        &self,
+        a: usize,
+        b: usize,
+        objects: &mut [Vertex],
+        config: &SimulationConfig,
+    ) -> bool {
+        let axis = config.minimum_image(objects[a].position - objects[b].position);
+        let dist = axis.magnitude();
+        if let Some(break_ratio) = self.break_ratio {
+            if dist > self.rest_length * break_ratio {
+                return false;
+            }
+        }
+        if dist == 0.0 {
+            return true;
+        }
+        let a_weight = objects[a].inverse_mass();
+        let b_weight = objects[b].inverse_mass();
+        let total_weight = a_weight + b_weight;
+        if total_weight == 0.0 {
+            return true;
+        }
+        let correction = axis / dist * (self.rest_length - dist) * self.stiffness;
+        objects[a].position += correction * (a_weight / total_weight);
+        objects[b].position -= correction * (b_weight / total_weight);
+        true
+    }
+}
+
+/// Stiffness and break ratio of every link a [`rope`], [`chain`] or [`cloth`] adds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkParams {
+    /// See [`Link::stiffness`].
+    pub stiffness: f32,
+    /// See [`Link::break_ratio`].
+    pub break_ratio: Option<f32>,
+}
+
+impl Default for LinkParams {
+    fn default() -> Self {
+        Self {
+            stiffness: 1.0,
+            break_ratio: None,
+        }
+    }
+}
+
+impl LinkParams {
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    pub fn with_break_ratio(mut self, break_ratio: f32) -> Self {
+        self.break_ratio = Some(break_ratio);
+        self
+    }
+
+    /// A link between `a` and `b` with these parameters.
+    pub fn link(self, a: ParticleId, b: ParticleId, rest_length: f32) -> Link {
+        Link {
+            a,
+            b,
+            rest_length,
+            stiffness: self.stiffness,
+            break_ratio: self.break_ratio,
+        }
+    }
+}
+
+/// Adds `segments + 1` copies of `template` from `start` to `end`, each linked to the next,
+/// and returns their handles.
+pub fn rope(
+    solver: &mut Solver,
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    segments: usize,
+    template: &Vertex,
+    params: LinkParams,
+) -> Vec<ParticleId> {
+    let step = (end - start) / segments as f32;
+    let ids: Vec<ParticleId> = (0..=segments)
+        .map(|i| solver.add(particle_at(template, start + step * i as f32)))
+        .collect();
+    for pair in ids.windows(2) {
+        solver.add_link(params.link(pair[0], pair[1], step.magnitude()));
+    }
+    ids
+}
+
+/// A rope of rigid links that snap when stretched beyond `break_ratio`.
+pub fn chain(
+    solver: &mut Solver,
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    segments: usize,
+    template: &Vertex,
+    break_ratio: f32,
+) -> Vec<ParticleId> {
+    let params = LinkParams::default().with_break_ratio(break_ratio);
+    rope(solver, start, end, segments, template, params)
+}
+
+/// Adds a `columns` x `rows` sheet hanging down from `top_left`, with structural links
+/// between horizontal and vertical neighbours. Returns handles row by row, top row first.
+pub fn cloth(
+    solver: &mut Solver,
+    top_left: Vector2<f32>,
+    columns: usize,
+    rows: usize,
+    spacing: f32,
+    template: &Vertex,
+    params: LinkParams,
+) -> Vec<ParticleId> {
+    let mut ids = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let position = top_left + cgmath::vec2(column as f32, -(row as f32)) * spacing;
+            ids.push(solver.add(particle_at(template, position)));
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let id = ids[row * columns + column];
+            if column + 1 < columns {
+                solver.add_link(params.link(id, ids[row * columns + column + 1], spacing));
+            }
+            if row + 1 < rows {
+                solver.add_link(params.link(id, ids[(row + 1) * columns + column], spacing));
+            }
+        }
+    }
+    ids
+}
+
+fn particle_at(template: &Vertex, position: Vector2<f32>) -> Vertex {
+    Vertex {
+        position,
+        previous_position: position,
+        ..template.clone()
+    }
+}
@@ -1,37 +1,23 @@
-extern crate cgmath;
 extern crate gl;
 extern crate glfw;
 
-use std::mem::size_of;
-
-use cgmath::*;
-use cgmath::num_traits::ToPrimitive;
-use gl::types::*;
 use glfw::{Action, Context, Key};
-use rand::{self, Rng};
 
-use crate::engine::Engine;
+use physics_engine::config::SimulationConfig;
+use physics_engine::Engine;
+
+use crate::renderer::Renderer;
 use crate::resource_manager::ResourceManager;
 
-mod colorgen;
-mod engine;
-mod grid;
 mod grid_renderer;
+mod lines_renderer;
 mod particles_renderer;
+mod renderer;
 mod resource_manager;
 mod shader;
-mod solver;
-mod vertex;
 
 const SCREEN_WIDTH: u32 = 1600;
 const SCREEN_HEIGHT: u32 = 1200;
-const RADIUS: f32 = 0.5;
-
-const WORLD_SIZE: Vector2<f32> = cgmath::vec2(300.0, 300.0);
-
-const CELL_WIDTH: f32 = RADIUS * 4.0;
-const GRID_WIDTH: usize = (WORLD_SIZE.x / CELL_WIDTH) as usize;
-const GRID_HEIGHT: usize = (WORLD_SIZE.y / CELL_WIDTH) as usize;
 
 fn main() {
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
@@ -59,7 +45,9 @@ fn main() {
     resource_manager.load_shader("cell");
     resource_manager.load_shader("particle");
 
-    let mut engine = Engine::new(&resource_manager);
+    let config = parse_config();
+    let renderer = Renderer::new(&resource_manager, &config);
+    let mut engine = Engine::new(config);
 
     window.set_key_polling(true);
     window.set_framebuffer_size_callback(|_window, width, height| unsafe {
@@ -72,138 +60,18 @@ fn main() {
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
     }
 
-    let mut vao: GLuint = 0;
-    let mut vertex_vbo: GLuint = 0;
-    let mut positions_vbo: GLuint = 0;
-
-    let mut cells_vao: GLuint = 0;
-    let mut cells_vbo: GLuint = 0;
-    let mut cells: Vec<cgmath::Vector2<f32>> = Vec::with_capacity(GRID_WIDTH * GRID_HEIGHT);
-    for x in (0..=GRID_HEIGHT) {
-        for y in 0..=GRID_WIDTH {
-            cells.push(cgmath::vec2(x as f32 * CELL_WIDTH, y as f32 * CELL_WIDTH));
-        }
-    }
-    unsafe {
-        gl::GenVertexArrays(1, &mut cells_vao);
-        gl::GenBuffers(1, &mut cells_vbo);
-        gl::BindVertexArray(cells_vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, cells_vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            cells.len() as isize * size_of::<cgmath::Vector2<f32>>() as GLsizeiptr,
-            cells.as_ptr().cast(),
-            gl::STATIC_DRAW,
-        );
-        gl::VertexAttribPointer(
-            0,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            size_of::<Vector2<f32>>() as GLint,
-            0 as *const _,
-        );
-        gl::EnableVertexAttribArray(0);
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
-    }
-
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1, &mut vertex_vbo);
-        gl::GenBuffers(1, &mut positions_vbo);
-        gl::BindVertexArray(vao);
-
-        // positions.
-        gl::BindBuffer(gl::ARRAY_BUFFER, positions_vbo);
-        let positions_data = &engine
-            .solver
-            .get_objects()
-            .iter()
-            .map(|y| y.position)
-            .collect::<Vec<cgmath::Vector2<f32>>>();
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            positions_data.len() as isize * size_of::<cgmath::Vector2<f32>>() as GLsizeiptr,
-            positions_data.as_ptr().cast(),
-            gl::DYNAMIC_DRAW,
-        );
-        gl::VertexAttribPointer(
-            0,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            size_of::<Vector2<f32>>() as GLint,
-            0 as *const _,
-        );
-        gl::EnableVertexAttribArray(0);
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::VertexAttribDivisor(0, 1);
-
-        // Unbind VAO.
-        gl::BindVertexArray(0);
-    }
-
     unsafe {
         gl::Enable(gl::PROGRAM_POINT_SIZE);
     }
 
-    // 60 fps
-    let delta_time = 1.0 / 60.0;
     while !window.should_close() {
-        engine.update(delta_time as f32);
-
-        unsafe {
-            // update positions buffer in video memory
-            gl::BindBuffer(gl::ARRAY_BUFFER, positions_vbo);
-            let positions_data = &engine
-                .solver
-                .get_objects()
-                .iter()
-                .map(|y| y.position)
-                .collect::<Vec<_>>();
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                positions_data.len() as isize * size_of::<cgmath::Vector2<f32>>() as GLsizeiptr,
-                positions_data.as_ptr().cast(),
-                gl::DYNAMIC_DRAW,
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-            // update colors
-            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_vbo);
-            let data_bytes = &engine
-                .solver
-                .get_objects()
-                .iter()
-                .map(|y| y.color)
-                .collect::<Vec<_>>();
-
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                data_bytes.len() as isize * size_of::<cgmath::Vector3<f32>>() as GLsizeiptr,
-                data_bytes.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-            // radius attribute
-            gl::VertexAttribPointer(
-                1,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                size_of::<cgmath::Vector3<f32>>() as GLint,
-                0 as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::VertexAttribDivisor(1, 1);
-        }
+        engine.tick();
 
         unsafe {
             gl::ClearColor(0.0, 0.0, 0.0, 0.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
-            engine.render();
+            renderer.render(&engine);
         }
 
         window.swap_buffers();
@@ -242,6 +110,33 @@ fn main() {
                 glfw::WindowEvent::Key(Key::Down, _, Action::Press, _) => {
                     engine.change_gravity(-0.0, -100.0);
                 }
+                glfw::WindowEvent::Key(Key::C, _, Action::Press, _) => {
+                    engine.add_cloth(100.0, 250.0);
+                }
+                glfw::WindowEvent::Key(Key::F, _, Action::Press, _) => {
+                    engine.add_funnel(150.0, 150.0);
+                }
+                glfw::WindowEvent::Key(Key::O, _, Action::Press, _) => {
+                    engine.next_container();
+                }
+                glfw::WindowEvent::Key(Key::K, _, Action::Press, _) => {
+                    engine.add_drain(20.0, 20.0);
+                }
+                glfw::WindowEvent::Key(Key::V, _, Action::Press, _) => {
+                    engine.add_vortex(150.0, 150.0);
+                }
+                glfw::WindowEvent::Key(Key::P, _, Action::Press, _) => {
+                    engine.toggle_planet();
+                }
+                glfw::WindowEvent::Key(Key::N, _, Action::Press, _) => {
+                    engine.toggle_n_body();
+                }
+                glfw::WindowEvent::Key(Key::L, _, Action::Press, _) => {
+                    engine.add_water(150.0, 250.0);
+                }
+                glfw::WindowEvent::Key(Key::M, _, Action::Press, _) => {
+                    engine.add_mud(150.0, 250.0);
+                }
                 glfw::WindowEvent::Key(Key::G, _, Action::Press, _) => {
                     engine.toggle_add_objects();
                 }
@@ -250,3 +145,16 @@ fn main() {
         }
     }
 }
+
+/// Reads an optional `WIDTH HEIGHT` world size from the command line.
+fn parse_config() -> SimulationConfig {
+    let mut config = SimulationConfig::default();
+    let args: Vec<f32> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("world size must be a number"))
+        .collect();
+    if let [width, height] = args[..] {
+        config.world_size = cgmath::vec2(width, height);
+    }
+    config
+}
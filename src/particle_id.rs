@@ -0,0 +1,138 @@
+/// Stable handle of a particle, unaffected by other particles being removed.
+///
+/// Slots of removed particles are reused with a new generation, so a handle to a removed
+/// particle never resolves to the particle that took its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParticleId {
+    slot: u32,
+    generation: u32,
+}
+
+impl ParticleId {
+    /// Index of the slot, smaller than the largest number of particles alive at once.
+    pub fn slot(&self) -> usize {
+        self.slot as usize
+    }
+}
+
+impl Handle for ParticleId {
+    fn from_parts(slot: u32, generation: u32) -> Self {
+        Self { slot, generation }
+    }
+
+    fn parts(self) -> (u32, u32) {
+        (self.slot, self.generation)
+    }
+}
+
+/// Slot and generation of a handle handed out by an [`IdTable`].
+pub(crate) trait Handle: Copy {
+    fn from_parts(slot: u32, generation: u32) -> Self;
+
+    fn parts(self) -> (u32, u32);
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    generation: u32,
+    index: usize,
+}
+
+/// Maps handles to their current index in the solver storage, particles unless told
+/// otherwise.
+#[derive(Debug)]
+pub(crate) struct IdTable<I = ParticleId> {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    /// Handle of the entry at each storage index.
+    ids: Vec<I>,
+}
+
+impl<I> Default for IdTable<I> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            ids: Vec::new(),
+        }
+    }
+}
+
+impl<I: Handle> IdTable<I> {
+    const REMOVED: usize = usize::MAX;
+
+    /// Hands out a handle for an entry pushed at the end of the storage.
+    pub fn push(&mut self) -> I {
+        let index = self.ids.len();
+        let id = match self.free.pop() {
+            Some(slot) => {
+                let entry = &mut self.slots[slot as usize];
+                entry.generation += 1;
+                entry.index = index;
+                I::from_parts(slot, entry.generation)
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index,
+                });
+                I::from_parts(self.slots.len() as u32 - 1, 0)
+            }
+        };
+        self.ids.push(id);
+        id
+    }
+
+    /// Mirrors a `swap_remove(index)` of the storage.
+    pub fn swap_remove(&mut self, index: usize) {
+        let removed = self.ids.swap_remove(index);
+        self.free_slot(removed);
+        if let Some(&moved) = self.ids.get(index) {
+            self.slots[moved.parts().0 as usize].index = index;
+        }
+    }
+
+    /// Mirrors a `retain` of the storage, `keep` is called with every storage index in order.
+    pub fn retain(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        let mut ids = std::mem::take(&mut self.ids);
+        let mut index = 0;
+        ids.retain(|&id| {
+            let kept = keep(index);
+            index += 1;
+            if !kept {
+                self.free_slot(id);
+            }
+            kept
+        });
+        self.ids = ids;
+        self.update_indices();
+    }
+
+    /// Mirrors a permutation of the storage, `order[new]` is the old index.
+    pub fn reorder(&mut self, order: &[usize]) {
+        self.ids = order.iter().map(|&old| self.ids[old]).collect();
+        self.update_indices();
+    }
+
+    pub fn index_of(&self, id: I) -> Option<usize> {
+        let (slot, generation) = id.parts();
+        let slot = self.slots.get(slot as usize)?;
+        (slot.generation == generation && slot.index != Self::REMOVED).then_some(slot.index)
+    }
+
+    pub fn ids(&self) -> &[I] {
+        &self.ids
+    }
+
+    fn free_slot(&mut self, id: I) {
+        let slot = id.parts().0;
+        self.slots[slot as usize].index = Self::REMOVED;
+        self.free.push(slot);
+    }
+
+    fn update_indices(&mut self) {
+        for (index, id) in self.ids.iter().enumerate() {
+            self.slots[id.parts().0 as usize].index = index;
+        }
+    }
+}
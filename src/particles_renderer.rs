@@ -1,10 +1,20 @@
-use std::mem::size_of;
+use std::mem::{offset_of, size_of};
+use std::ptr;
 
 use cgmath::{Matrix4, Vector2, Vector3};
 use gl::types::{GLint, GLsizeiptr, GLuint};
 
+use physics_engine::Vertex;
+
 use crate::resource_manager::ResourceManager;
-use crate::vertex::Vertex;
+
+/// Per-particle data uploaded to video memory.
+#[repr(C)]
+struct ParticleInstance {
+    position: Vector2<f32>,
+    color: Vector3<f32>,
+    radius: f32,
+}
 
 pub struct ParticlesRenderer<'a> {
     resource_manager: &'a ResourceManager,
@@ -20,7 +30,7 @@ impl<'a> ParticlesRenderer<'a> {
             gl::GenVertexArrays(1, &mut vao);
             gl::GenBuffers(1, &mut vbo);
         }
-        let mut renderer = Self {
+        let renderer = Self {
             resource_manager,
             vao,
             vbo,
@@ -30,19 +40,13 @@ impl<'a> ParticlesRenderer<'a> {
     }
 
     fn init_vao(&self) {
+        let stride = size_of::<ParticleInstance>() as GLint;
         unsafe {
             gl::BindVertexArray(self.vao);
 
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
             // positions.
-            gl::VertexAttribPointer(
-                0,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                (size_of::<Vector2<f32>>() + size_of::<Vector3<f32>>()) as GLint,
-                0 as *const _,
-            );
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, ptr::null());
             gl::EnableVertexAttribArray(0);
             gl::VertexAttribDivisor(0, 1);
 
@@ -52,31 +56,54 @@ impl<'a> ParticlesRenderer<'a> {
                 3,
                 gl::FLOAT,
                 gl::FALSE,
-                (size_of::<(Vector2<f32>, Vector3<f32>)>()) as GLint,
-                size_of::<Vector2<f32>>() as *const _,
+                stride,
+                offset_of!(ParticleInstance, color) as *const _,
             );
             gl::EnableVertexAttribArray(1);
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::VertexAttribDivisor(1, 1);
 
+            // radius
+            gl::VertexAttribPointer(
+                2,
+                1,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                offset_of!(ParticleInstance, radius) as *const _,
+            );
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribDivisor(2, 1);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
             // Unbind VAO.
             gl::BindVertexArray(0);
         }
     }
 
-    pub fn render(&self, projection: Matrix4<f32>, particles: &Vec<Vertex>) {
+    /// Draws particles at `positions`, `pixels_per_unit` converts world radius to point size.
+    pub fn render(
+        &self,
+        projection: Matrix4<f32>,
+        pixels_per_unit: f32,
+        particles: &[Vertex],
+        positions: impl Iterator<Item = Vector2<f32>>,
+    ) {
         unsafe {
             let particles_buffer = particles
                 .iter()
-                .map(|y| (y.position, y.color))
+                .zip(positions)
+                .map(|(y, position)| ParticleInstance {
+                    position,
+                    color: y.color,
+                    radius: y.radius,
+                })
                 .collect::<Vec<_>>();
 
-            // copy new positions and colors buffer in video memory - buffer already setup and configured.
+            // copy new particles buffer in video memory - buffer already setup and configured.
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                particles_buffer.len() as isize
-                    * size_of::<(Vector2<f32>, Vector3<f32>)>() as GLsizeiptr,
+                particles_buffer.len() as isize * size_of::<ParticleInstance>() as GLsizeiptr,
                 particles_buffer.as_ptr().cast(),
                 gl::DYNAMIC_DRAW,
             );
@@ -85,6 +112,7 @@ impl<'a> ParticlesRenderer<'a> {
             let particle_shader = self.resource_manager.get_shader("particle");
             particle_shader.use_shader();
             particle_shader.set_matrix4(projection, "projection");
+            particle_shader.set_float(pixels_per_unit, "pixels_per_unit");
             gl::BindVertexArray(self.vao);
             gl::DrawArraysInstanced(gl::POINTS, 0, 1, particles.len() as i32);
         }
@@ -0,0 +1,156 @@
+use cgmath::{vec2, Vector2};
+use rayon::prelude::*;
+
+use crate::broadphase::Broadphase;
+use crate::vertex::Vertex;
+
+const NO_CHILDREN: usize = usize::MAX;
+
+struct Node {
+    center: Vector2<f32>,
+    half_size: f32,
+    /// Index of the first of four consecutive children in `LooseQuadtree::nodes`.
+    children: usize,
+    objects: Vec<usize>,
+}
+
+impl Node {
+    fn new(center: Vector2<f32>, half_size: f32) -> Self {
+        Self {
+            center,
+            half_size,
+            children: NO_CHILDREN,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Loose bounds are twice the node size, so any object centered in the node
+    /// with a radius up to half the node size fits.
+    fn loose_overlaps(&self, center: Vector2<f32>, radius: f32) -> bool {
+        let reach = 2.0 * self.half_size + radius;
+        (center.x - self.center.x).abs() < reach && (center.y - self.center.y).abs() < reach
+    }
+}
+
+/// Loose quadtree rebuilt around the objects on every build.
+///
+/// Each object is stored in the deepest node that is at least as large as the object
+/// and contains its center, so large and small objects mix without large ones ending
+/// up in many cells.
+pub struct LooseQuadtree {
+    nodes: Vec<Node>,
+    bounds: Vec<(Vector2<f32>, f32)>,
+}
+
+impl LooseQuadtree {
+    const MAX_DEPTH: usize = 12;
+
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            bounds: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, id: usize) {
+        let (center, radius) = self.bounds[id];
+        let mut node = 0;
+        for _ in 0..Self::MAX_DEPTH {
+            let half_size = self.nodes[node].half_size / 2.0;
+            if half_size < radius {
+                break;
+            }
+            if self.nodes[node].children == NO_CHILDREN {
+                self.split(node);
+            }
+            let parent = &self.nodes[node];
+            let quadrant =
+                (center.x >= parent.center.x) as usize + 2 * (center.y >= parent.center.y) as usize;
+            node = parent.children + quadrant;
+        }
+        self.nodes[node].objects.push(id);
+    }
+
+    fn split(&mut self, node: usize) {
+        let Node {
+            center, half_size, ..
+        } = self.nodes[node];
+        let quarter = half_size / 2.0;
+        self.nodes[node].children = self.nodes.len();
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let child_center = center + vec2(x, y) * quarter;
+            self.nodes.push(Node::new(child_center, quarter));
+        }
+    }
+
+    /// Calls `found` for every object after `id` whose bounds overlap it.
+    fn query(&self, id: usize, mut found: impl FnMut(usize)) {
+        let (center, radius) = self.bounds[id];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // The root also holds objects too large for its loose bounds.
+            if index != 0 && !node.loose_overlaps(center, radius) {
+                continue;
+            }
+            for &other in node.objects.iter() {
+                let (other_center, other_radius) = self.bounds[other];
+                let reach = radius + other_radius;
+                if other > id
+                    && (center.x - other_center.x).abs() < reach
+                    && (center.y - other_center.y).abs() < reach
+                {
+                    found(other);
+                }
+            }
+            if node.children != NO_CHILDREN {
+                stack.extend(node.children..node.children + 4);
+            }
+        }
+    }
+}
+
+impl Default for LooseQuadtree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Broadphase for LooseQuadtree {
+    fn build(&mut self, objects: &[Vertex]) {
+        self.bounds.clear();
+        self.bounds.extend(
+            objects
+                .iter()
+                .map(|object| (object.position, object.radius)),
+        );
+        self.nodes.clear();
+        if objects.is_empty() {
+            return;
+        }
+
+        let mut min = vec2(f32::MAX, f32::MAX);
+        let mut max = vec2(f32::MIN, f32::MIN);
+        for (center, _) in self.bounds.iter() {
+            min = vec2(min.x.min(center.x), min.y.min(center.y));
+            max = vec2(max.x.max(center.x), max.y.max(center.y));
+        }
+        let half_size = 0.5 * (max.x - min.x).max(max.y - min.y) + f32::EPSILON;
+        self.nodes.push(Node::new((min + max) / 2.0, half_size));
+        for id in 0..objects.len() {
+            self.insert(id);
+        }
+    }
+
+    fn pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        pairs.clear();
+        if self.nodes.is_empty() {
+            return;
+        }
+        pairs.par_extend((0..self.bounds.len()).into_par_iter().flat_map_iter(|id| {
+            let mut found = Vec::new();
+            self.query(id, |other| found.push((id, other)));
+            found
+        }));
+    }
+}
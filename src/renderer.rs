@@ -0,0 +1,59 @@
+use cgmath::{ortho, Vector2};
+
+use physics_engine::config::SimulationConfig;
+use physics_engine::Engine;
+
+use crate::grid_renderer::GridRenderer;
+use crate::lines_renderer::LinesRenderer;
+use crate::particles_renderer::ParticlesRenderer;
+use crate::resource_manager::ResourceManager;
+
+pub struct Renderer<'a> {
+    grid_renderer: GridRenderer<'a>,
+    particles_renderer: ParticlesRenderer<'a>,
+    lines_renderer: LinesRenderer<'a>,
+    world_size: Vector2<f32>,
+    pub draw_grid: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(resource_manager: &'a ResourceManager, config: &SimulationConfig) -> Self {
+        Self {
+            grid_renderer: GridRenderer::new(resource_manager, config),
+            particles_renderer: ParticlesRenderer::new(resource_manager),
+            lines_renderer: LinesRenderer::new(resource_manager),
+            world_size: config.world_size,
+            draw_grid: false,
+        }
+    }
+
+    pub fn render(&self, engine: &Engine) {
+        let projection = ortho(0.0, self.world_size.x, 0.0, self.world_size.y, -1.0, 1.0);
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let pixels_per_unit = viewport[3] as f32 / self.world_size.y;
+        if self.draw_grid {
+            self.grid_renderer.render(projection);
+        }
+        let positions = engine.interpolated_positions().collect::<Vec<_>>();
+        self.particles_renderer.render(
+            projection,
+            pixels_per_unit,
+            engine.solver.get_objects(),
+            positions.iter().copied(),
+        );
+        self.lines_renderer
+            .render_links(projection, &engine.solver, &positions);
+        self.lines_renderer
+            .render_colliders(projection, engine.solver.get_colliders());
+        self.lines_renderer
+            .render_shapes(projection, engine.solver.get_kill_zones());
+        self.lines_renderer.render_container(
+            projection,
+            engine.solver.container(),
+            engine.solver.time(),
+        );
+    }
+}
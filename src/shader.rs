@@ -116,4 +116,11 @@ impl Shader {
             );
         }
     }
+
+    pub fn set_float(&self, value: f32, name: &str) {
+        let cname = CString::new(name).expect("float name");
+        unsafe {
+            gl::Uniform1f(gl::GetUniformLocation(self.id, cname.as_ptr()), value);
+        }
+    }
 }
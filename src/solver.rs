@@ -1,158 +1,1037 @@
-use cgmath::InnerSpace;
+use std::sync::Arc;
 
-use crate::{CELL_WIDTH, GRID_HEIGHT, GRID_WIDTH, RADIUS, WORLD_SIZE};
-use crate::grid::Grid;
-use crate::vertex::Vertex;
+use cgmath::{InnerSpace, Vector2};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::broadphase::Broadphase;
+use crate::collider::{Collider, ColliderSet, Shape};
+use crate::config::SimulationConfig;
+use crate::container::Container;
+use crate::emitter::{Emitter, EmitterId};
+use crate::fluid::FluidSolver;
+use crate::force_field::ForceField;
+use crate::gravity::{BarnesHut, NBody, PointGravity};
+use crate::grid::DensestCell;
+use crate::link::Link;
+use crate::particle_id::{IdTable, ParticleId};
+use crate::vertex::{Motion, Vertex};
+
+/// Position of a kinematic object as a function of simulation time.
+pub type Path = Box<dyn Fn(f32) -> Vector2<f32> + Send + Sync>;
 
 pub struct Solver {
-    gravity: cgmath::Vector2<f32>,
+    config: SimulationConfig,
     objects: Vec<Vertex>,
-    grid: Grid,
+    ids: IdTable,
+    links: Vec<Link>,
+    paths: Vec<(ParticleId, Path)>,
+    colliders: ColliderSet,
+    kill_zones: Vec<Shape>,
+    emitters: Vec<Emitter>,
+    emitter_ids: IdTable<EmitterId>,
+    /// Emitters added so far, seeds the ones without a seed of their own.
+    emitters_added: u64,
+    force_fields: Vec<ForceField>,
+    container: Container,
+    time: f32,
+    /// Sub-step of the last update, used to read velocities between updates.
+    sub_dt: f32,
+    /// Velocities to give objects at the start of the next update, once its sub-step is known.
+    launches: Vec<(ParticleId, Vector2<f32>)>,
+    updates: u32,
+    broadphase: Box<dyn Broadphase>,
+    /// Objects grown by half the cohesion range, built into the broadphase instead.
+    broadphase_proxies: Vec<Vertex>,
+    n_body_tree: BarnesHut,
+    fluid_solver: FluidSolver,
+    stripes: PairStripes,
+    densest_cell: DensestCell,
+    /// Pool of the solver's own threads, `None` runs on the global rayon pool.
+    thread_pool: Option<Arc<ThreadPool>>,
+}
+
+/// Objects shared between threads while solving collisions.
+///
+/// Each stripe of [`PairStripes`] only touches objects in itself and in the next
+/// stripe. Stripes solved in the same pass are separated by at least one other
+/// stripe, so no object is ever borrowed by two threads at once.
+#[derive(Clone, Copy)]
+struct SharedObjects(*mut Vertex);
+
+unsafe impl Send for SharedObjects {}
+unsafe impl Sync for SharedObjects {}
+
+impl SharedObjects {
+    /// # Safety
+    ///
+    /// `index` must be in bounds and no other reference to the object may be alive.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get(&self, index: usize) -> &mut Vertex {
+        &mut *self.0.add(index)
+    }
+}
+
+/// Vertical stripes of the world, at least a cell wide, along x.
+#[derive(Clone, Copy, Debug)]
+struct StripeLayout {
+    origin: f32,
+    width: f32,
+    count: usize,
+    /// The last stripe borders the first one across a periodic x axis.
+    wraps: bool,
+    world_width: f32,
+}
+
+impl StripeLayout {
+    fn stripe(&self, mut x: f32) -> usize {
+        if self.wraps {
+            x = x.rem_euclid(self.world_width);
+        }
+        (((x - self.origin) / self.width) as usize).min(self.count - 1)
+    }
+
+    fn next(&self, stripe: usize) -> usize {
+        if self.wraps {
+            (stripe + 1) % self.count
+        } else {
+            stripe + 1
+        }
+    }
+}
+
+/// Candidate pairs bucketed by the stripe of their left object.
+#[derive(Default)]
+struct PairStripes {
+    pairs: Vec<(usize, usize)>,
+    pair_stripes: Vec<usize>,
+    sorted: Vec<(usize, usize)>,
+    start: Vec<usize>,
+}
+
+impl PairStripes {
+    const DROPPED: usize = usize::MAX;
+
+    /// Sorts `pairs` into stripes, keeping their order within a stripe. Pairs more than a
+    /// stripe apart cannot be touching and are dropped.
+    fn sort(&mut self, objects: &[Vertex], layout: StripeLayout) {
+        self.pair_stripes.clear();
+        self.pair_stripes
+            .par_extend(self.pairs.par_iter().map(|&(a, b)| {
+                let a = layout.stripe(objects[a].position.x);
+                let b = layout.stripe(objects[b].position.x);
+                if a == b || layout.next(a) == b {
+                    a
+                } else if layout.next(b) == a {
+                    b
+                } else {
+                    Self::DROPPED
+                }
+            }));
+
+        self.start.clear();
+        self.start.resize(layout.count + 1, 0);
+        for &stripe in self.pair_stripes.iter() {
+            if stripe != Self::DROPPED {
+                self.start[stripe + 1] += 1;
+            }
+        }
+        for stripe in 0..layout.count {
+            self.start[stripe + 1] += self.start[stripe];
+        }
+        self.sorted.resize(self.start[layout.count], (0, 0));
+        let mut cursor = self.start.clone();
+        for (&pair, &stripe) in self.pairs.iter().zip(self.pair_stripes.iter()) {
+            if stripe != Self::DROPPED {
+                self.sorted[cursor[stripe]] = pair;
+                cursor[stripe] += 1;
+            }
+        }
+    }
+
+    fn get(&self, stripe: usize) -> &[(usize, usize)] {
+        &self.sorted[self.start[stripe]..self.start[stripe + 1]]
+    }
 }
 
 impl Solver {
-    pub fn new(objects: Vec<Vertex>) -> Self {
+    /// Stripe count in deterministic mode, which must not depend on the thread count.
+    const DETERMINISTIC_STRIPES: usize = 64;
+    /// Every pair is solved this many times per sub-step, dense piles get soft with fewer.
+    const COLLISION_ITERATIONS: usize = 2;
+    const COLLIDER_CELLS_PER_GRID_CELL: f32 = 8.0;
+
+    pub fn new(mut config: SimulationConfig, objects: Vec<Vertex>) -> Self {
+        for object in objects.iter() {
+            Self::fit_cells(&mut config, object.radius);
+        }
+        let broadphase = config.broadphase.create(&config);
+        let colliders = ColliderSet::new(Self::COLLIDER_CELLS_PER_GRID_CELL * config.cell_width);
+        // Solvers without a thread count share the global pool, so several worlds in one
+        // process do not each start a thread per core.
+        let thread_pool = (config.threads > 0).then(|| {
+            let thread_pool = ThreadPoolBuilder::new()
+                .num_threads(config.threads)
+                .build()
+                .expect("failed to build solver thread pool");
+            Arc::new(thread_pool)
+        });
+        let sub_dt = config.time_step / config.sub_steps.max(1) as f32;
+        let mut ids = IdTable::default();
+        for _ in objects.iter() {
+            ids.push();
+        }
         Self {
-            gravity: cgmath::vec2(0.0, -1000.0),
+            config,
             objects,
-            grid: Grid::new(),
+            ids,
+            links: Vec::new(),
+            paths: Vec::new(),
+            colliders,
+            kill_zones: Vec::new(),
+            emitters: Vec::new(),
+            emitter_ids: IdTable::default(),
+            emitters_added: 0,
+            force_fields: Vec::new(),
+            container: Container::default(),
+            time: 0.0,
+            sub_dt,
+            launches: Vec::new(),
+            updates: 0,
+            broadphase,
+            broadphase_proxies: Vec::new(),
+            n_body_tree: BarnesHut::default(),
+            fluid_solver: FluidSolver::default(),
+            stripes: PairStripes::default(),
+            densest_cell: DensestCell::default(),
+            thread_pool,
         }
     }
 
-    pub fn add(&mut self, object: Vertex) {
-        self.objects.push(object)
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Adds an object and returns its handle.
+    pub fn add(&mut self, object: Vertex) -> ParticleId {
+        if Self::fit_cells(&mut self.config, object.radius) {
+            self.broadphase = self.config.broadphase.create(&self.config);
+        }
+        self.objects.push(object);
+        self.ids.push()
+    }
+
+    /// Adds an object moving at `velocity` when the next update starts.
+    pub fn add_with_velocity(&mut self, object: Vertex, velocity: Vector2<f32>) -> ParticleId {
+        let id = self.add(object);
+        self.launches.push((id, velocity));
+        id
+    }
+
+    /// Gives the object `velocity` at the start of the next update, converted for the sub-step
+    /// of that update. Does nothing once the object was removed.
+    pub fn set_velocity(&mut self, id: ParticleId, velocity: Vector2<f32>) {
+        self.launches.retain(|(launched, _)| *launched != id);
+        self.launches.push((id, velocity));
+    }
+
+    /// Velocity of the object over the last sub-step, or the one it will be given by
+    /// [`Solver::set_velocity`].
+    pub fn velocity(&self, id: ParticleId) -> Option<Vector2<f32>> {
+        let object = self.get(id)?;
+        match self
+            .launches
+            .iter()
+            .rev()
+            .find(|(launched, _)| *launched == id)
+        {
+            Some(&(_, velocity)) => Some(velocity),
+            None => Some(object.velocity(self.sub_dt)),
+        }
     }
 
+    /// All objects in storage order. The order changes when objects are removed and when
+    /// the solver sorts its storage, keep a [`ParticleId`] to follow an object.
     pub fn get_objects(&self) -> &Vec<Vertex> {
-        return &self.objects;
+        &self.objects
+    }
+
+    /// The object behind `id`, `None` once it was removed.
+    pub fn get(&self, id: ParticleId) -> Option<&Vertex> {
+        let index = self.index_of(id)?;
+        Some(&self.objects[index])
+    }
+
+    pub fn get_mut(&mut self, id: ParticleId) -> Option<&mut Vertex> {
+        let index = self.index_of(id)?;
+        Some(&mut self.objects[index])
+    }
+
+    /// Handle of the object at `index`.
+    pub fn id(&self, index: usize) -> ParticleId {
+        self.ids.ids()[index]
+    }
+
+    /// Handles of all objects, in storage order.
+    pub fn ids(&self) -> &[ParticleId] {
+        self.ids.ids()
+    }
+
+    /// Current index of the object, `None` once it was removed.
+    pub fn index_of(&self, id: ParticleId) -> Option<usize> {
+        self.ids.index_of(id)
+    }
+
+    /// Removes the object by moving the last object into its place.
+    /// Links and paths of the removed object are dropped.
+    pub fn remove(&mut self, id: ParticleId) -> Option<Vertex> {
+        let index = self.index_of(id)?;
+        let object = self.objects.swap_remove(index);
+        self.ids.swap_remove(index);
+        self.drop_dangling_references();
+        Some(object)
+    }
+
+    /// Removes every object for which `keep` returns `false` and returns how many went.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Vertex) -> bool) -> usize {
+        let count = self.objects.len();
+        let mut index = 0;
+        while index < self.objects.len() {
+            if keep(&self.objects[index]) {
+                index += 1;
+            } else {
+                self.objects.swap_remove(index);
+                self.ids.swap_remove(index);
+            }
+        }
+        let removed = count - self.objects.len();
+        if removed > 0 {
+            self.drop_dangling_references();
+        }
+        removed
+    }
+
+    /// Objects touching a kill zone are removed at the end of every update.
+    pub fn add_kill_zone(&mut self, zone: Shape) -> usize {
+        self.kill_zones.push(zone);
+        self.kill_zones.len() - 1
+    }
+
+    pub fn get_kill_zones(&self) -> &[Shape] {
+        &self.kill_zones
+    }
+
+    /// Emitters run at the start of every update, in the order they were added. Finished
+    /// emitters are dropped after their last particles, their handles stop resolving.
+    pub fn add_emitter(&mut self, mut emitter: Emitter) -> EmitterId {
+        emitter.seed_default(self.emitters_added);
+        self.emitters_added += 1;
+        self.emitters.push(emitter);
+        self.emitter_ids.push()
+    }
+
+    pub fn get_emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    pub fn emitter(&self, id: EmitterId) -> Option<&Emitter> {
+        self.emitters.get(self.emitter_ids.index_of(id)?)
+    }
+
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut Emitter> {
+        let index = self.emitter_ids.index_of(id)?;
+        self.emitters.get_mut(index)
+    }
+
+    /// Force fields act on dynamic objects every sub-step, after gravity.
+    pub fn add_force_field(&mut self, field: ForceField) -> usize {
+        self.force_fields.push(field);
+        self.force_fields.len() - 1
+    }
+
+    pub fn get_force_fields(&self) -> &[ForceField] {
+        &self.force_fields
+    }
+
+    pub fn force_field_mut(&mut self, index: usize) -> Option<&mut ForceField> {
+        self.force_fields.get_mut(index)
+    }
+
+    pub fn add_link(&mut self, link: Link) {
+        self.links.push(link);
+    }
+
+    /// Does nothing once the object was removed.
+    pub fn set_motion(&mut self, id: ParticleId, motion: Motion) {
+        let Some(object) = self.get_mut(id) else {
+            return;
+        };
+        object.motion = motion;
+        if motion != Motion::Kinematic {
+            self.paths.retain(|(path_id, _)| *path_id != id);
+        }
+    }
+
+    /// Makes the object kinematic and moves it along `path` from now on.
+    /// Does nothing once the object was removed.
+    pub fn set_path(
+        &mut self,
+        id: ParticleId,
+        path: impl Fn(f32) -> Vector2<f32> + Send + Sync + 'static,
+    ) {
+        let time = self.time;
+        let Some(object) = self.get_mut(id) else {
+            return;
+        };
+        object.motion = Motion::Kinematic;
+        object.explicit_velocity = cgmath::vec2(0.0, 0.0);
+        object.position = path(time);
+        object.previous_position = object.position;
+        self.paths.retain(|(path_id, _)| *path_id != id);
+        self.paths.push((id, Box::new(path)));
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) -> usize {
+        self.colliders.add(collider)
+    }
+
+    pub fn get_colliders(&self) -> &[Collider] {
+        self.colliders.get_colliders()
+    }
+
+    pub fn set_container(&mut self, container: Container) {
+        self.container = container;
+    }
+
+    pub fn container(&self) -> &Container {
+        &self.container
+    }
+
+    /// Simulated time in seconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Links that are still intact, broken links are dropped during the update.
+    pub fn get_links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// The most populated grid cell seen during the sub-steps of the last update.
+    pub fn densest_cell(&self) -> DensestCell {
+        self.densest_cell
     }
 
     pub fn update(&mut self, dt: f32) {
-        let sub_steps = 8;
+        self.densest_cell = DensestCell::default();
+        let sub_steps = self.config.sub_steps.max(1);
         let sub_dt = dt / sub_steps as f32;
+        self.sub_dt = sub_dt;
+        self.emit(dt);
+        self.launch(sub_dt);
 
-        for _ in 0..sub_steps {
-            // let start = Instant::now();
-            self.apply_gravity(dt);
-            // let gravity = start.elapsed();
-            // let start = Instant::now();
-            self.add_objects_to_grid();
-            // let grid = start.elapsed();
-            // let start = Instant::now();
-            self.solve_collisions();
-            // let collisions = start.elapsed();
-            // let start = Instant::now();
-            self.apply_constraints();
-            // let constraints = start.elapsed();
-            // let start = Instant::now();
-            self.update_positions(sub_dt);
-            // let positions = start.elapsed();
-            // println!("gravity: {gravity:?}, constr: {constraints:?}, grid: {grid:?}, collisions: {collisions:?}, positions: {positions:?}");
+        match self.thread_pool.clone() {
+            Some(thread_pool) => thread_pool.install(|| {
+                for _ in 0..sub_steps {
+                    self.sub_step(sub_dt);
+                }
+            }),
+            None => {
+                for _ in 0..sub_steps {
+                    self.sub_step(sub_dt);
+                }
+            }
         }
+        self.despawn(dt);
+
+        self.updates = self.updates.wrapping_add(1);
+        let sort_interval = self.config.sort_interval;
+        if sort_interval > 0 && self.updates.is_multiple_of(sort_interval) {
+            self.sort_objects();
+        }
+    }
+
+    fn sub_step(&mut self, sub_dt: f32) {
+        // let start = Instant::now();
+        self.apply_gravity();
+        self.apply_force_fields();
+        // let gravity = start.elapsed();
+        // let start = Instant::now();
+        self.build_broadphase();
+        // let grid = start.elapsed();
+        // let start = Instant::now();
+        self.solve_collisions(sub_dt);
+        self.fluid_solver
+            .solve(&mut self.objects, &self.config, sub_dt);
+        self.solve_links();
+        self.solve_static_collisions(sub_dt);
+        // let collisions = start.elapsed();
+        // let start = Instant::now();
+        self.apply_constraints(sub_dt);
+        // let constraints = start.elapsed();
+        // let start = Instant::now();
+        self.apply_damping(sub_dt);
+        self.update_positions(sub_dt);
+        self.wrap_positions();
+        // let positions = start.elapsed();
+        // println!("gravity: {gravity:?}, constr: {constraints:?}, grid: {grid:?}, collisions: {collisions:?}, positions: {positions:?}");
+    }
+
+    pub fn set_sub_steps(&mut self, sub_steps: u32) {
+        self.config.sub_steps = sub_steps.max(1);
     }
 
     pub fn change_gravity(&mut self, x: f32, y: f32) {
-        self.gravity = cgmath::vec2(x, y);
+        self.config.gravity = cgmath::vec2(x, y);
     }
 
-    fn update_positions(&mut self, dt: f32) {
-        for object in self.objects.iter_mut() {
-            object.update_position(dt);
+    pub fn set_point_gravity(&mut self, point_gravity: Option<PointGravity>) {
+        self.config.point_gravity = point_gravity;
+    }
+
+    pub fn set_n_body(&mut self, n_body: Option<NBody>) {
+        self.config.n_body = n_body;
+    }
+
+    /// Changes the gap over which cohesion and adhesion pull, growing the grid cells to fit.
+    pub fn set_cohesion_range(&mut self, range: f32) {
+        self.config.cohesion_range = range;
+        let largest = self
+            .objects
+            .iter()
+            .map(|object| object.radius)
+            .fold(0.0, f32::max);
+        if Self::fit_cells(&mut self.config, largest) {
+            self.broadphase = self.config.broadphase.create(&self.config);
         }
     }
 
-    fn apply_gravity(&mut self, dt: f32) {
+    /// Grows the grid cells to fit objects of `radius` and everything within the cohesion
+    /// range of them, returns whether they grew.
+    fn fit_cells(config: &mut SimulationConfig, radius: f32) -> bool {
+        let reach = 2.0 * radius + config.cohesion_range;
+        let grow = reach > config.cell_width;
+        if grow {
+            config.cell_width = reach;
+        }
+        grow
+    }
+
+    fn emit(&mut self, dt: f32) {
+        let mut emitters = std::mem::take(&mut self.emitters);
+        for emitter in emitters.iter_mut() {
+            emitter.emit(dt, self.time, |object, velocity| {
+                self.add_with_velocity(object, velocity);
+            });
+        }
+        self.emitter_ids
+            .retain(|index| !emitters[index].is_finished());
+        emitters.retain(|emitter| !emitter.is_finished());
+        self.emitters = emitters;
+    }
+
+    /// Turns queued launch velocities into the integrator state for a sub-step of `sub_dt`.
+    fn launch(&mut self, sub_dt: f32) {
+        for (id, velocity) in std::mem::take(&mut self.launches) {
+            if let Some(index) = self.ids.index_of(id) {
+                self.objects[index].set_velocity(velocity, sub_dt);
+            }
+        }
+    }
+
+    /// Ages objects and removes the expired ones and those touching a kill zone.
+    fn despawn(&mut self, dt: f32) {
+        let mut any_dead = false;
         for object in self.objects.iter_mut() {
-            object.accelerate(self.gravity);
+            object.age += dt;
+            any_dead |= object.is_expired();
+        }
+        if !any_dead && self.kill_zones.is_empty() {
+            return;
         }
+        let kill_zones = std::mem::take(&mut self.kill_zones);
+        self.retain(|object| {
+            !object.is_expired()
+                && kill_zones
+                    .iter()
+                    .all(|zone| zone.signed_distance(object.position).0 >= object.radius)
+        });
+        self.kill_zones = kill_zones;
     }
 
-    fn apply_constraints(&mut self) {
+    /// Drops links and paths whose object was removed.
+    fn drop_dangling_references(&mut self) {
+        let ids = &self.ids;
+        self.links
+            .retain(|link| ids.index_of(link.a).is_some() && ids.index_of(link.b).is_some());
+        self.paths.retain(|(id, _)| ids.index_of(*id).is_some());
+    }
+
+    /// Orders the storage by grid cell so that objects close in the world are close in
+    /// memory. Handles follow their objects, links and paths are stored by handle.
+    fn sort_objects(&mut self) {
+        let cell_width = self.config.cell_width;
+        let cells: Vec<(i32, i32)> = self
+            .objects
+            .iter()
+            .map(|object| {
+                let cell = object.position / cell_width;
+                (cell.x.floor() as i32, cell.y.floor() as i32)
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..self.objects.len()).collect();
+        order.sort_by_key(|&index| cells[index]);
+        if order.iter().enumerate().all(|(new, &old)| new == old) {
+            return;
+        }
+        self.objects = order.iter().map(|&old| self.objects[old].clone()).collect();
+        self.ids.reorder(&order);
+    }
+
+    fn solve_links(&mut self) {
+        let objects = &mut self.objects;
+        let ids = &self.ids;
+        let config = &self.config;
+        self.links
+            .retain(|link| match (ids.index_of(link.a), ids.index_of(link.b)) {
+                (Some(a), Some(b)) => link.solve(a, b, objects, config),
+                _ => false,
+            });
+    }
+
+    fn update_positions(&mut self, dt: f32) {
+        // Paths of removed objects are dropped right away, every handle resolves.
+        let path_starts: Vec<(usize, Vector2<f32>)> = self
+            .paths
+            .iter()
+            .filter_map(|(id, _)| self.ids.index_of(*id))
+            .map(|index| (index, self.objects[index].position))
+            .collect();
+
+        self.objects
+            .par_iter_mut()
+            .for_each(|object| object.update_position(dt));
+
+        // Objects on a path move by the path alone, whatever the integrator did.
+        self.time += dt;
+        for ((_, path), (index, start)) in self.paths.iter().zip(path_starts) {
+            let object = &mut self.objects[index];
+            object.explicit_velocity = cgmath::vec2(0.0, 0.0);
+            object.previous_position = start;
+            object.position = path(self.time);
+        }
+    }
+
+    /// Moves objects that left through a periodic side back in on the opposite one.
+    fn wrap_positions(&mut self) {
+        let periodic = self.config.periodic;
+        if !periodic.x && !periodic.y {
+            return;
+        }
+        let world_size = self.config.world_size;
+        self.objects.par_iter_mut().for_each(|object| {
+            if object.motion != Motion::Dynamic {
+                return;
+            }
+            let mut shift = cgmath::vec2(0.0, 0.0);
+            if periodic.x {
+                shift.x = object.position.x.rem_euclid(world_size.x) - object.position.x;
+            }
+            if periodic.y {
+                shift.y = object.position.y.rem_euclid(world_size.y) - object.position.y;
+            }
+            object.position += shift;
+            object.previous_position += shift;
+        });
+    }
+
+    fn apply_gravity(&mut self) {
+        let config = &self.config;
+        match config.point_gravity {
+            Some(point_gravity) => self.objects.par_iter_mut().for_each(|object| {
+                let offset = config.minimum_image(object.position - point_gravity.center);
+                object.accelerate(point_gravity.acceleration(offset));
+            }),
+            None => {
+                let gravity = config.gravity;
+                self.objects
+                    .par_iter_mut()
+                    .for_each(|object| object.accelerate(gravity));
+            }
+        }
+        if let Some(n_body) = config.n_body {
+            self.n_body_tree.apply(&mut self.objects, n_body);
+        }
+    }
+
+    fn apply_force_fields(&mut self) {
+        if self.force_fields.is_empty() {
+            return;
+        }
+        let fields = &self.force_fields;
+        let config = &self.config;
+        let time = self.time;
+        self.objects
+            .par_iter_mut()
+            .filter(|object| object.motion == Motion::Dynamic)
+            .for_each(|object| {
+                let acceleration = fields
+                    .iter()
+                    .map(|field| field.acceleration(object, time, config))
+                    .fold(cgmath::vec2(0.0, 0.0), |sum, acceleration| {
+                        sum + acceleration
+                    });
+                object.accelerate(acceleration);
+            });
+    }
+
+    fn apply_damping(&mut self, dt: f32) {
+        let linear_damping = self.config.linear_damping;
+        let air_drag = self.config.air_drag;
+        if linear_damping == 0.0 && air_drag == 0.0 {
+            return;
+        }
+        self.objects.par_iter_mut().for_each(|object| {
+            if object.motion != Motion::Dynamic {
+                return;
+            }
+            let velocity = object.velocity(dt);
+            // Implicit form keeps strong damping from reversing the velocity.
+            let drag = linear_damping + air_drag * velocity.magnitude();
+            object.set_velocity(velocity / (1.0 + drag * dt), dt);
+        });
+    }
+
+    fn apply_constraints(&mut self, dt: f32) {
+        match self.container {
+            Container::Walls => {}
+            Container::Open => return,
+            _ => {
+                self.apply_container(dt);
+                return;
+            }
+        }
         // TODO: считать только по бокам границы.
         // if x == 0 || x == GRID_WIDTH - 1 || y == 0 || y == GRID_HEIGHT - 1 {
-        for object in self.objects.iter_mut() {
-            if object.position.x - RADIUS <= 0.0 {
-                object.position.x = RADIUS;
-            } else if object.position.x + RADIUS >= WORLD_SIZE.x {
-                object.position.x = WORLD_SIZE.x - RADIUS;
+        let world_size = self.config.world_size;
+        let wall_friction = self.config.wall_friction;
+        let wall_restitution = self.config.wall_restitution;
+        let wall_adhesion = self.config.wall_adhesion;
+        let range = self.config.cohesion_range;
+        let periodic = self.config.periodic;
+        let still = cgmath::vec2(0.0, 0.0);
+        self.objects.par_iter_mut().for_each(|object| {
+            if object.motion != Motion::Dynamic {
+                return;
             }
-            if object.position.y - RADIUS <= 0.0 {
-                object.position.y = 0.0 + RADIUS;
-            } else if object.position.y + RADIUS >= WORLD_SIZE.y {
-                object.position.y = WORLD_SIZE.y - RADIUS;
+            let radius = object.radius;
+            let friction = (object.friction * wall_friction).sqrt();
+            if !periodic.x && object.position.x - radius <= 0.0 {
+                let depth = radius - object.position.x;
+                let restitution = wall_restitution.left.max(object.restitution);
+                let normal = cgmath::vec2(1.0, 0.0);
+                Self::resolve_contact(object, normal, depth, still, restitution, friction, dt);
+            } else if !periodic.x && object.position.x + radius >= world_size.x {
+                let depth = object.position.x + radius - world_size.x;
+                let restitution = wall_restitution.right.max(object.restitution);
+                let normal = cgmath::vec2(-1.0, 0.0);
+                Self::resolve_contact(object, normal, depth, still, restitution, friction, dt);
             }
-        }
+            if !periodic.y && object.position.y - radius <= 0.0 {
+                let depth = radius - object.position.y;
+                let restitution = wall_restitution.bottom.max(object.restitution);
+                let normal = cgmath::vec2(0.0, 1.0);
+                Self::resolve_contact(object, normal, depth, still, restitution, friction, dt);
+            } else if !periodic.y && object.position.y + radius >= world_size.y {
+                let depth = object.position.y + radius - world_size.y;
+                let restitution = wall_restitution.top.max(object.restitution);
+                let normal = cgmath::vec2(0.0, -1.0);
+                Self::resolve_contact(object, normal, depth, still, restitution, friction, dt);
+            }
+            let adhesion = object.adhesion * wall_adhesion;
+            if adhesion > 0.0 {
+                let (x, y) = (object.position.x, object.position.y);
+                if !periodic.x {
+                    let gap = x - radius;
+                    Self::adhere(object, cgmath::vec2(1.0, 0.0), gap, adhesion, range);
+                    let gap = world_size.x - x - radius;
+                    Self::adhere(object, cgmath::vec2(-1.0, 0.0), gap, adhesion, range);
+                }
+                if !periodic.y {
+                    let gap = y - radius;
+                    Self::adhere(object, cgmath::vec2(0.0, 1.0), gap, adhesion, range);
+                    let gap = world_size.y - y - radius;
+                    Self::adhere(object, cgmath::vec2(0.0, -1.0), gap, adhesion, range);
+                }
+            }
+        });
     }
 
-    fn solve_collisions(&mut self) {
-        for row in 0..GRID_WIDTH {
-            for column in 0..GRID_HEIGHT {
-                self.collide_cell(row, column);
+    fn apply_container(&mut self, dt: f32) {
+        let wall_friction = self.config.wall_friction;
+        let wall_adhesion = self.config.wall_adhesion;
+        let cohesion_range = self.config.cohesion_range;
+        let container = &self.container;
+        let time = self.time;
+        self.objects.par_iter_mut().for_each(|object| {
+            if object.motion != Motion::Dynamic {
+                return;
             }
+            let friction = (object.friction * wall_friction).sqrt();
+            let restitution = object.restitution;
+            let adhesion = object.adhesion * wall_adhesion;
+            // Walls within the adhesion band count as contacts, with the depth shifted back.
+            let range = if adhesion > 0.0 { cohesion_range } else { 0.0 };
+            container.contacts(object.position, object.radius + range, time, |contact| {
+                let depth = contact.depth - range;
+                if depth > 0.0 {
+                    Self::resolve_contact(
+                        object,
+                        contact.normal,
+                        depth,
+                        contact.surface_velocity,
+                        restitution,
+                        friction,
+                        dt,
+                    );
+                }
+                Self::adhere(object, contact.normal, -depth, adhesion, range);
+            });
+        });
+    }
+
+    fn solve_static_collisions(&mut self, dt: f32) {
+        if self.colliders.is_empty() {
+            return;
         }
+        let range = self.config.cohesion_range;
+        self.colliders.prepare(0.5 * self.config.cell_width + range);
+        let colliders = &self.colliders;
+        self.objects.par_iter_mut().for_each(|object| {
+            if object.motion != Motion::Dynamic {
+                return;
+            }
+            for &index in colliders.candidates(object.position) {
+                let collider = &colliders.get_colliders()[index];
+                let (distance, normal) = collider.shape.signed_distance(object.position);
+                if distance < object.radius {
+                    let depth = object.radius - distance;
+                    let restitution = collider.restitution.max(object.restitution);
+                    let friction = (object.friction * collider.friction).sqrt();
+                    let still = cgmath::vec2(0.0, 0.0);
+                    Self::resolve_contact(object, normal, depth, still, restitution, friction, dt);
+                }
+                let adhesion = object.adhesion * collider.adhesion;
+                Self::adhere(object, normal, distance - object.radius, adhesion, range);
+            }
+        });
     }
 
-    fn add_objects_to_grid(&mut self) {
-        self.grid.clear();
-        for (i, object) in self.objects.iter().enumerate() {
-            self.grid
-                .add_object(object.position.x, object.position.y, i);
+    /// Pushes `object` out along `normal` by `depth`, then reflects the velocity it hit the
+    /// surface with and applies Coulomb friction bounded by the penetration depth. Both act
+    /// on the velocity relative to the surface.
+    fn resolve_contact(
+        object: &mut Vertex,
+        normal: Vector2<f32>,
+        depth: f32,
+        surface_velocity: Vector2<f32>,
+        restitution: f32,
+        friction: f32,
+        dt: f32,
+    ) {
+        let mut velocity = object.velocity(dt) - surface_velocity;
+        object.position += normal * depth;
+        let approach = velocity.dot(normal);
+        if approach < 0.0 {
+            velocity -= normal * approach * (1.0 + restitution);
         }
+        if friction > 0.0 {
+            let tangent = cgmath::vec2(-normal.y, normal.x);
+            let max_change = friction * depth / dt;
+            let change = velocity.dot(tangent).clamp(-max_change, max_change);
+            velocity -= tangent * change;
+        }
+        object.set_velocity(velocity + surface_velocity, dt);
     }
 
-    fn collide_cell(&mut self, row: usize, column: usize) {
-        for object_1_idx in self.grid.get_cell_objects(row, column) {
-            for neighbour_row in -1..=1 {
-                for neighbour_column in -1..=1 {
-                    let row_idx = row as i32 - neighbour_row;
-                    let col_idx = column as i32 - neighbour_column;
-                    if row_idx < 0
-                        || col_idx < 0
-                        || row_idx >= GRID_WIDTH as i32
-                        || col_idx >= GRID_HEIGHT as i32
-                    {
-                        continue;
-                    }
-                    Self::collide_nearby_cells(
-                        &mut self.objects,
-                        &self.grid,
-                        *object_1_idx,
-                        row_idx as usize,
-                        col_idx as usize,
-                    );
+    /// Pulls `object` back along `normal` towards a surface its edge is `gap` away from,
+    /// while the gap is within `range`.
+    fn adhere(object: &mut Vertex, normal: Vector2<f32>, gap: f32, adhesion: f32, range: f32) {
+        if adhesion > 0.0 && range > 0.0 && gap < range {
+            object.accelerate(-normal * (adhesion * object.inverse_mass()));
+        }
+    }
+
+    /// Solves candidate pairs in stripes, even stripes first and odd stripes second, so
+    /// that stripes running in parallel never share objects. With a periodic x axis and an
+    /// odd stripe count the first and last stripes touch across the seam, so the last one
+    /// is solved alone in a third pass.
+    fn solve_collisions(&mut self, dt: f32) {
+        self.broadphase.pairs(&mut self.stripes.pairs);
+        let layout = self.stripe_layout();
+        self.stripes.sort(&self.objects, layout);
+
+        let seam_pass = layout.wraps && layout.count > 1 && layout.count % 2 == 1;
+        let parallel_stripes = if seam_pass {
+            layout.count - 1
+        } else {
+            layout.count
+        };
+        let objects = SharedObjects(self.objects.as_mut_ptr());
+        let stripes = &self.stripes;
+        let config = &self.config;
+        // Cohesion is a force, so it is only added once per sub-step.
+        let collide_stripe = |stripe: usize, cohere: bool| {
+            for &(a, b) in stripes.get(stripe) {
+                // SAFETY: both objects belong to this stripe or the next, see `SharedObjects`.
+                let (lhs, rhs) = unsafe { (objects.get(a), objects.get(b)) };
+                let axis = config.minimum_image(lhs.position - rhs.position);
+                Self::collide_objects(lhs, rhs, axis, dt);
+                if cohere {
+                    Self::cohere_objects(lhs, rhs, axis, config.cohesion_range);
                 }
             }
+        };
+        for iteration in 0..Self::COLLISION_ITERATIONS {
+            let cohere = iteration == 0 && config.cohesion_range > 0.0;
+            for pass in 0..2 {
+                (pass..parallel_stripes)
+                    .into_par_iter()
+                    .step_by(2)
+                    .for_each(|stripe| collide_stripe(stripe, cohere));
+            }
+            if seam_pass {
+                collide_stripe(layout.count - 1, cohere);
+            }
         }
     }
 
-    fn collide_nearby_cells(
-        objects: &mut Vec<Vertex>,
-        grid: &Grid,
-        object_1_idx: usize,
-        row: usize,
-        column: usize,
-    ) {
-        for object_2_idx in grid.get_cell_objects(row, column) {
-            Self::collide_objects(objects, object_1_idx, *object_2_idx)
+    fn stripe_layout(&self) -> StripeLayout {
+        let max_stripes = if self.config.deterministic {
+            Self::DETERMINISTIC_STRIPES
+        } else {
+            2 * rayon::current_num_threads()
+        };
+        let min_width = self.config.cell_width;
+        let world_width = self.config.world_size.x;
+        if self.config.periodic.x {
+            let count = ((world_width / min_width) as usize).clamp(1, max_stripes);
+            return StripeLayout {
+                origin: 0.0,
+                width: world_width / count as f32,
+                count,
+                wraps: true,
+                world_width,
+            };
+        }
+        let (min, max) = self
+            .objects
+            .par_iter()
+            .map(|object| (object.position.x, object.position.x))
+            .reduce(
+                || (f32::MAX, f32::MIN),
+                |lhs, rhs| (lhs.0.min(rhs.0), lhs.1.max(rhs.1)),
+            );
+        let extent = (max - min).max(0.0);
+        let width = (extent / max_stripes as f32).max(min_width);
+        StripeLayout {
+            origin: min,
+            width,
+            count: (extent / width) as usize + 1,
+            wraps: false,
+            world_width,
         }
     }
 
-    fn collide_objects(objects: &mut Vec<Vertex>, object_1_idx: usize, object_2_idx: usize) {
-        if object_1_idx == object_2_idx {
-            return;
+    fn build_broadphase(&mut self) {
+        let range = self.config.cohesion_range;
+        if range > 0.0 {
+            // Overlapping proxies make pairs within cohesion range candidates as well.
+            self.broadphase_proxies.clear();
+            self.broadphase_proxies
+                .extend(self.objects.iter().map(|object| {
+                    Vertex::new(object.position, object.color)
+                        .with_radius(object.radius + range / 2.0)
+                }));
+            self.broadphase.build(&self.broadphase_proxies);
+        } else {
+            self.broadphase.build(&self.objects);
+        }
+        if self.broadphase.densest_cell().count > self.densest_cell.count {
+            self.densest_cell = self.broadphase.densest_cell();
         }
-        let lhs_pos = objects[object_1_idx].position;
-        let rhs_pos = objects[object_2_idx].position;
-        let collision_axis = lhs_pos - rhs_pos;
+    }
+
+    /// `collision_axis` points from `rhs` to `lhs`, across the seam on periodic axes.
+    fn collide_objects(lhs: &mut Vertex, rhs: &mut Vertex, collision_axis: Vector2<f32>, dt: f32) {
         let dist2 = collision_axis.magnitude2();
-        if dist2 < CELL_WIDTH.powf(2.0) {
+        let min_dist = lhs.radius + rhs.radius;
+        let lhs_weight = lhs.inverse_mass();
+        let rhs_weight = rhs.inverse_mass();
+        let total_weight = lhs_weight + rhs_weight;
+        if dist2 < min_dist * min_dist && dist2 > 0.0 && total_weight > 0.0 {
             let dist = dist2.sqrt();
             let normalized = collision_axis / dist;
-            let delta = CELL_WIDTH - dist;
-            let lhs = &mut objects[object_1_idx];
-            lhs.position = lhs.position + 0.5 * normalized * delta;
-            let rhs = &mut objects[object_2_idx];
-            rhs.position = rhs.position - 0.5 * normalized * delta;
+            let delta = min_dist - dist;
+            let lhs_share = lhs_weight / total_weight;
+            let rhs_share = rhs_weight / total_weight;
+            let mut lhs_velocity = lhs.velocity(dt);
+            let mut rhs_velocity = rhs.velocity(dt);
+            lhs.position += lhs_share * normalized * delta;
+            rhs.position -= rhs_share * normalized * delta;
+
+            let approach = (lhs_velocity - rhs_velocity).dot(normalized);
+            if approach < 0.0 {
+                // Replace the impact velocity with its reflection instead of the correction.
+                let restitution = lhs.restitution.max(rhs.restitution);
+                let impulse = -(1.0 + restitution) * approach;
+                lhs_velocity += normalized * impulse * lhs_share;
+                rhs_velocity -= normalized * impulse * rhs_share;
+                Self::set_movable_velocity(lhs, lhs_velocity, dt);
+                Self::set_movable_velocity(rhs, rhs_velocity, dt);
+            }
+
+            let friction = (lhs.friction * rhs.friction).sqrt();
+            if friction > 0.0 {
+                // Coulomb friction: tangential change is bounded by the normal correction.
+                let tangent = cgmath::vec2(-normalized.y, normalized.x);
+                let lhs_velocity = lhs.velocity(dt);
+                let rhs_velocity = rhs.velocity(dt);
+                let max_change = friction * delta / dt;
+                let change = (lhs_velocity - rhs_velocity)
+                    .dot(tangent)
+                    .clamp(-max_change, max_change);
+                Self::set_movable_velocity(lhs, lhs_velocity - tangent * change * lhs_share, dt);
+                Self::set_movable_velocity(rhs, rhs_velocity + tangent * change * rhs_share, dt);
+            }
+        }
+    }
+
+    /// Pulls `lhs` and `rhs` towards each other while they are less than `range` apart.
+    /// Equal and opposite forces, so lighter objects are pulled harder.
+    fn cohere_objects(
+        lhs: &mut Vertex,
+        rhs: &mut Vertex,
+        collision_axis: Vector2<f32>,
+        range: f32,
+    ) {
+        let cohesion = (lhs.cohesion * rhs.cohesion).sqrt();
+        if cohesion <= 0.0 {
+            return;
+        }
+        let max_dist = lhs.radius + rhs.radius + range;
+        let dist2 = collision_axis.magnitude2();
+        if dist2 >= max_dist * max_dist || dist2 == 0.0 {
+            return;
+        }
+        let pull = collision_axis / dist2.sqrt() * cohesion;
+        lhs.accelerate(-pull * lhs.inverse_mass());
+        rhs.accelerate(pull * rhs.inverse_mass());
+    }
+
+    fn set_movable_velocity(object: &mut Vertex, velocity: Vector2<f32>, dt: f32) {
+        if object.motion == Motion::Dynamic {
+            object.set_velocity(velocity, dt);
         }
     }
 }
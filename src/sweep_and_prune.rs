@@ -0,0 +1,73 @@
+use rayon::prelude::*;
+
+use crate::broadphase::Broadphase;
+use crate::vertex::Vertex;
+
+/// Object bounds along the sweep axis plus what the pair test needs.
+#[derive(Clone, Copy, Debug)]
+struct Interval {
+    min_x: f32,
+    max_x: f32,
+    y: f32,
+    radius: f32,
+    id: usize,
+}
+
+/// Sorts objects by their left edge and sweeps along x, pairing every object with the
+/// ones that start before it ends.
+///
+/// Works for any mix of sizes and any world extent. The order is kept between builds, so
+/// the sort is cheap while objects move little.
+#[derive(Default)]
+pub struct SweepAndPrune {
+    intervals: Vec<Interval>,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Broadphase for SweepAndPrune {
+    fn build(&mut self, objects: &[Vertex]) {
+        if self.intervals.len() != objects.len() {
+            self.intervals = (0..objects.len())
+                .map(|id| Interval {
+                    min_x: 0.0,
+                    max_x: 0.0,
+                    y: 0.0,
+                    radius: 0.0,
+                    id,
+                })
+                .collect();
+        }
+        self.intervals.par_iter_mut().for_each(|interval| {
+            let object = &objects[interval.id];
+            interval.min_x = object.position.x - object.radius;
+            interval.max_x = object.position.x + object.radius;
+            interval.y = object.position.y;
+            interval.radius = object.radius;
+        });
+        // Stable and adaptive, nearly sorted input from the last build is close to linear.
+        self.intervals
+            .sort_by(|lhs, rhs| lhs.min_x.total_cmp(&rhs.min_x));
+    }
+
+    fn pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        pairs.clear();
+        let intervals = &self.intervals;
+        pairs.par_extend(
+            intervals
+                .par_iter()
+                .enumerate()
+                .flat_map_iter(|(index, lhs)| {
+                    intervals[index + 1..]
+                        .iter()
+                        .take_while(move |rhs| rhs.min_x < lhs.max_x)
+                        .filter(move |rhs| (lhs.y - rhs.y).abs() < lhs.radius + rhs.radius)
+                        .map(move |rhs| (lhs.id.min(rhs.id), lhs.id.max(rhs.id)))
+                }),
+        );
+    }
+}
@@ -1,31 +1,224 @@
-#[repr(C, packed)]
-#[derive(PartialEq, Debug)]
+/// Numerical scheme used to advance a [`Vertex`].
+///
+/// Every scheme treats `position - previous_position` as movement that happened since
+/// the last step (including collision corrections), so the rest of the solver can keep
+/// working with positions only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// Velocity is implied by `position - previous_position`.
+    #[default]
+    PositionVerlet,
+    /// Explicit velocity, position is advanced with `v * dt + a * dt^2 / 2` and the
+    /// velocity with the average of the old and new acceleration.
+    VelocityVerlet,
+    /// Explicit velocity, updated before the position.
+    SemiImplicitEuler,
+}
+
+/// How a [`Vertex`] responds to forces and collisions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Motion {
+    #[default]
+    Dynamic,
+    /// Never moves, but other particles still collide with it.
+    Pinned,
+    /// Keeps its velocity or follows a path set with `Solver::set_path`,
+    /// ignoring forces and collisions.
+    Kinematic,
+}
+
+/// Whether a [`Vertex`] is a grain or part of a fluid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Phase {
+    #[default]
+    Solid,
+    /// Also keeps the density of its neighbourhood at rest, see [`crate::Fluid`].
+    Fluid,
+}
+
+#[repr(C)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Vertex {
     pub position: cgmath::Vector2<f32>,
     pub previous_position: cgmath::Vector2<f32>,
+    /// Velocity kept by the explicit integrators, always zero for position Verlet. Read the
+    /// velocity of any integrator with [`Vertex::velocity`].
+    pub explicit_velocity: cgmath::Vector2<f32>,
     pub acceleration: cgmath::Vector2<f32>,
+    /// Acceleration of the last velocity Verlet step, `None` right after the velocity was
+    /// set, when there is no estimate to correct.
+    pub previous_acceleration: Option<cgmath::Vector2<f32>>,
     pub color: cgmath::Vector3<f32>,
+    pub radius: f32,
+    pub mass: f32,
+    /// Tangential friction coefficient used against other particles and walls.
+    pub friction: f32,
+    /// Share of the normal velocity kept after an impact, `0` sticks and `1` bounces fully.
+    pub restitution: f32,
+    /// Pull towards particles less than `SimulationConfig::cohesion_range` apart, as an
+    /// acceleration of a unit mass. Combined with the other particle as `sqrt(a * b)`.
+    pub cohesion: f32,
+    /// Pull towards walls and colliders within the same range, scaled by their adhesion.
+    pub adhesion: f32,
+    pub integrator: Integrator,
+    pub motion: Motion,
+    pub phase: Phase,
+    /// Seconds since the vertex was added to a solver.
+    pub age: f32,
+    /// The solver removes the vertex once `age` reaches it.
+    pub lifetime: Option<f32>,
 }
 
 impl Vertex {
+    pub const DEFAULT_RADIUS: f32 = 1.0;
+
     pub fn new(position: cgmath::Vector2<f32>, color: cgmath::Vector3<f32>) -> Self {
         Self {
             position,
             previous_position: position,
+            explicit_velocity: cgmath::vec2(0.0, 0.0),
             acceleration: cgmath::vec2(0.0, 0.0),
-            color: color,
+            previous_acceleration: None,
+            color,
+            radius: Self::DEFAULT_RADIUS,
+            mass: 1.0,
+            friction: 0.0,
+            restitution: 0.0,
+            cohesion: 0.0,
+            adhesion: 0.0,
+            integrator: Integrator::default(),
+            motion: Motion::default(),
+            phase: Phase::default(),
+            age: 0.0,
+            lifetime: None,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_cohesion(mut self, cohesion: f32) -> Self {
+        self.cohesion = cohesion;
+        self
+    }
+
+    pub fn with_adhesion(mut self, adhesion: f32) -> Self {
+        self.adhesion = adhesion;
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.motion = motion;
+        self
+    }
+
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_lifetime(mut self, lifetime: f32) -> Self {
+        self.lifetime = Some(lifetime);
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.lifetime.is_some_and(|lifetime| self.age >= lifetime)
+    }
+
+    /// Inverse mass used to share corrections, zero for objects that can't be pushed.
+    pub fn inverse_mass(&self) -> f32 {
+        match self.motion {
+            Motion::Dynamic => 1.0 / self.mass,
+            Motion::Pinned | Motion::Kinematic => 0.0,
         }
     }
 
     pub fn update_position(&mut self, dt: f32) {
-        let velocity = self.position - self.previous_position;
-        let position = self.position;
-        self.previous_position = position;
-        self.position = position + velocity + self.acceleration * (dt * dt);
+        match self.motion {
+            Motion::Dynamic => {}
+            Motion::Pinned => {
+                self.previous_position = self.position;
+                self.explicit_velocity = cgmath::vec2(0.0, 0.0);
+                self.acceleration = cgmath::vec2(0.0, 0.0);
+                self.previous_acceleration = None;
+                return;
+            }
+            Motion::Kinematic => self.acceleration = cgmath::vec2(0.0, 0.0),
+        }
+        let displacement = self.position - self.previous_position;
+        match self.integrator {
+            Integrator::PositionVerlet => {
+                self.previous_position = self.position;
+                self.position += displacement + self.acceleration * (dt * dt);
+            }
+            Integrator::VelocityVerlet => {
+                // The last step estimated the velocity with the old acceleration alone,
+                // completing it gives `v += (a_old + a_new) * dt / 2`.
+                let correction = self
+                    .previous_acceleration
+                    .map_or(cgmath::vec2(0.0, 0.0), |previous| {
+                        (self.acceleration - previous) * (0.5 * dt)
+                    });
+                let velocity = self.explicit_velocity + displacement / dt + correction;
+                self.position += velocity * dt + self.acceleration * (0.5 * dt * dt);
+                self.explicit_velocity = velocity + self.acceleration * dt;
+                self.previous_acceleration = Some(self.acceleration);
+                self.previous_position = self.position;
+            }
+            Integrator::SemiImplicitEuler => {
+                self.explicit_velocity += displacement / dt + self.acceleration * dt;
+                self.position += self.explicit_velocity * dt;
+                self.previous_position = self.position;
+            }
+        }
         self.acceleration = cgmath::vec2(0.0, 0.0);
     }
 
+    /// Velocity for a step of `dt`, including movement from collision corrections.
+    pub fn velocity(&self, dt: f32) -> cgmath::Vector2<f32> {
+        self.explicit_velocity + (self.position - self.previous_position) / dt
+    }
+
+    /// Sets the velocity for a step of `dt` without moving the vertex. Position Verlet
+    /// stores it as `previous_position = position - velocity * dt`, so `dt` must be the
+    /// sub-step the solver runs next.
+    pub fn set_velocity(&mut self, velocity: cgmath::Vector2<f32>, dt: f32) {
+        match self.integrator {
+            Integrator::PositionVerlet => {
+                self.previous_position = self.position - velocity * dt;
+            }
+            Integrator::VelocityVerlet | Integrator::SemiImplicitEuler => {
+                self.explicit_velocity = velocity;
+                self.previous_position = self.position;
+                self.previous_acceleration = None;
+            }
+        }
+    }
+
     pub fn accelerate(&mut self, acceleration: cgmath::Vector2<f32>) {
-        self.acceleration = self.acceleration + acceleration;
+        self.acceleration += acceleration;
     }
 }
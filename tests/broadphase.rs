@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use cgmath::InnerSpace;
+use physics_engine::{
+    Broadphase, BroadphaseKind, Grid, HashGrid, LooseQuadtree, Periodic, SimulationConfig, Solver,
+    SweepAndPrune, Vertex,
+};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+const KINDS: [BroadphaseKind; 4] = [
+    BroadphaseKind::Grid,
+    BroadphaseKind::HashGrid,
+    BroadphaseKind::SweepAndPrune,
+    BroadphaseKind::Quadtree,
+];
+
+/// A mix of small grains and a few boulders.
+fn mixed_objects() -> Vec<Vertex> {
+    (0..800)
+        .map(|i| {
+            let x = 20.0 + (i % 40) as f32 * 1.7 + (i * 7 % 10) as f32 * 0.05;
+            let y = 20.0 + (i / 40) as f32 * 1.7;
+            let radius = if i % 50 == 0 { 6.0 } else { 1.0 };
+            particle(x, y).with_radius(radius)
+        })
+        .collect()
+}
+
+fn touching_pairs(objects: &[Vertex]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for i in 0..objects.len() {
+        for j in i + 1..objects.len() {
+            let distance = (objects[i].position - objects[j].position).magnitude();
+            if distance < objects[i].radius + objects[j].radius {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+#[test]
+fn every_broadphase_reports_touching_pairs_once() {
+    let objects = mixed_objects();
+    let config = SimulationConfig {
+        cell_width: 12.0,
+        ..SimulationConfig::default()
+    };
+    let broadphases: Vec<Box<dyn Broadphase>> = vec![
+        Box::new(Grid::new(&config)),
+        Box::new(HashGrid::new(config.cell_width)),
+        Box::new(SweepAndPrune::new()),
+        Box::new(LooseQuadtree::new()),
+    ];
+    let expected = touching_pairs(&objects);
+    assert!(expected.len() > 100);
+
+    for mut broadphase in broadphases {
+        broadphase.build(&objects);
+        let mut pairs = Vec::new();
+        broadphase.pairs(&mut pairs);
+        assert!(pairs.iter().all(|(i, j)| i < j));
+        let unique: HashSet<_> = pairs.iter().copied().collect();
+        assert_eq!(unique.len(), pairs.len());
+        for pair in expected.iter() {
+            assert!(unique.contains(pair), "missing {pair:?}");
+        }
+    }
+}
+
+#[test]
+fn every_broadphase_separates_a_mixed_pile() {
+    // Touching pairs each broadphase finds in the same pile, as the solver builds them.
+    let pile = mixed_objects();
+    let touching = |kind: BroadphaseKind| {
+        let config = SimulationConfig {
+            broadphase: kind,
+            ..SimulationConfig::default()
+        };
+        let solver = Solver::new(config, pile.clone());
+        let mut broadphase = kind.create(solver.config());
+        broadphase.build(&pile);
+        let mut pairs = Vec::new();
+        broadphase.pairs(&mut pairs);
+        pairs
+            .into_iter()
+            .filter(|&(i, j)| {
+                let distance = (pile[i].position - pile[j].position).magnitude();
+                distance < pile[i].radius + pile[j].radius
+            })
+            .collect::<HashSet<_>>()
+    };
+    let expected = touching(BroadphaseKind::Grid);
+    assert!(expected.len() > 100);
+
+    for kind in KINDS {
+        assert_eq!(touching(kind), expected, "{kind:?}");
+
+        // Without gravity and with heavy damping the pile comes to rest, so any overlap
+        // left is a pair the broadphase missed.
+        let config = SimulationConfig {
+            broadphase: kind,
+            deterministic: true,
+            linear_damping: 20.0,
+            ..floating()
+        };
+        let mut solver = Solver::new(config, pile.clone());
+        run(&mut solver, 30);
+        let objects = solver.get_objects();
+        let overlap = touching_pairs(objects)
+            .into_iter()
+            .map(|(i, j)| {
+                let distance = (objects[i].position - objects[j].position).magnitude();
+                objects[i].radius + objects[j].radius - distance
+            })
+            .fold(0.0, f32::max);
+        // A tenth of the smallest radius.
+        assert!(overlap < 0.1, "{kind:?} left an overlap of {overlap}");
+    }
+}
+
+#[test]
+fn every_broadphase_collides_across_periodic_seams() {
+    for kind in KINDS {
+        let config = SimulationConfig {
+            periodic: Periodic { x: true, y: false },
+            broadphase: kind,
+            ..floating()
+        };
+        let objects = vec![particle(0.5, 150.0), particle(299.7, 150.0)];
+        let mut solver = Solver::new(config, objects);
+        solver.update(DT);
+        let objects = solver.get_objects();
+        let gap = objects[0].position.x + 300.0 - objects[1].position.x;
+        assert!(gap >= 2.0 - 1e-3, "{kind:?} left a gap of {gap}");
+    }
+}
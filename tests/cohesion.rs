@@ -0,0 +1,155 @@
+use cgmath::InnerSpace;
+
+use physics_engine::{BroadphaseKind, Collider, Container, SimulationConfig, Solver};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+fn sticky() -> SimulationConfig {
+    SimulationConfig {
+        cohesion_range: 0.5,
+        ..floating()
+    }
+}
+
+/// Distance between two particles of the given cohesion after a second, starting with a
+/// gap of `gap` between their edges.
+fn pulled(config: SimulationConfig, cohesion: f32, gap: f32) -> f32 {
+    let mut solver = Solver::new(config, Vec::new());
+    let a = solver.add(particle(150.0, 150.0).with_cohesion(cohesion));
+    let b = solver.add(particle(152.0 + gap, 150.0).with_cohesion(cohesion));
+    run(&mut solver, 60);
+    (solver.get(a).unwrap().position - solver.get(b).unwrap().position).magnitude()
+}
+
+#[test]
+fn cohesion_closes_gaps_within_range_with_every_broadphase() {
+    for broadphase in [
+        BroadphaseKind::Grid,
+        BroadphaseKind::HashGrid,
+        BroadphaseKind::SweepAndPrune,
+        BroadphaseKind::Quadtree,
+    ] {
+        let config = SimulationConfig {
+            broadphase,
+            ..sticky()
+        };
+        let distance = pulled(config, 100.0, 0.3);
+        assert!((distance - 2.0).abs() < 0.05, "{broadphase:?}: {distance}");
+    }
+}
+
+#[test]
+fn cohesion_only_reaches_across_the_range() {
+    assert!((pulled(sticky(), 100.0, 0.8) - 2.8).abs() < 1e-4);
+    assert!((pulled(sticky(), 0.0, 0.3) - 2.3).abs() < 1e-4);
+    let config = SimulationConfig {
+        cohesion_range: 0.0,
+        ..sticky()
+    };
+    assert!((pulled(config, 100.0, 0.3) - 2.3).abs() < 1e-4);
+}
+
+#[test]
+fn changing_the_range_sizes_cells_like_a_new_solver() {
+    let objects = || {
+        vec![
+            particle(100.0, 100.0).with_radius(0.5),
+            particle(200.0, 100.0).with_radius(3.0),
+        ]
+    };
+    let built = Solver::new(sticky(), objects());
+    let mut changed = Solver::new(floating(), objects());
+    changed.set_cohesion_range(0.5);
+    assert_eq!(changed.config().cell_width, built.config().cell_width);
+    assert_eq!(built.config().cell_width, 6.5);
+
+    // Small particles need no larger cells than the default.
+    let small = || vec![particle(100.0, 100.0).with_radius(0.5)];
+    let mut changed = Solver::new(floating(), small());
+    changed.set_cohesion_range(0.5);
+    assert_eq!(
+        changed.config().cell_width,
+        Solver::new(sticky(), small()).config().cell_width
+    );
+}
+
+#[test]
+fn cohesion_conserves_momentum() {
+    let mut solver = Solver::new(sticky(), Vec::new());
+    let light = solver.add(particle(150.0, 150.0).with_cohesion(100.0));
+    let heavy = solver.add(particle(152.4, 150.0).with_cohesion(100.0).with_mass(4.0));
+    solver.update(DT);
+    let light = solver.velocity(light).unwrap();
+    let heavy = solver.velocity(heavy).unwrap();
+    assert!(light.x > 0.0);
+    assert!(
+        (light.x + 4.0 * heavy.x).abs() < 0.02 * light.x,
+        "{light:?} {heavy:?}"
+    );
+}
+
+#[test]
+fn adhesion_holds_particles_against_gravity() {
+    let hang = |adhesion: f32, container: Container, collider: Option<Collider>| {
+        let config = SimulationConfig {
+            gravity: cgmath::vec2(0.0, -1000.0),
+            ..sticky()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        solver.set_container(container);
+        if let Some(collider) = collider {
+            solver.add_collider(collider);
+        }
+        let id = solver.add(particle(150.0, 299.0).with_adhesion(adhesion));
+        run(&mut solver, 60);
+        solver.get(id).unwrap().position.y
+    };
+    // Under the ceiling of the world box.
+    assert!(hang(2000.0, Container::Walls, None) > 298.5);
+    assert!(hang(500.0, Container::Walls, None) < 200.0);
+
+    // Inside the top of a circular container.
+    let circle = || Container::Circle {
+        center: cgmath::vec2(150.0, 150.0),
+        radius: 150.0,
+    };
+    assert!(hang(2000.0, circle(), None) > 298.5);
+    assert!(hang(500.0, circle(), None) < 200.0);
+
+    // Below a collider, unless it is made slippery.
+    let shelf = || Collider::aabb(cgmath::vec2(100.0, 300.0), cgmath::vec2(200.0, 310.0));
+    assert!(hang(2000.0, Container::Open, Some(shelf())) > 298.5);
+    let slippery = shelf().with_adhesion(0.0);
+    assert!(hang(2000.0, Container::Open, Some(slippery)) < 200.0);
+}
+
+#[test]
+fn wet_sand_holds_taller_piles_than_dry_sand() {
+    let settle = |cohesion: f32| {
+        let config = SimulationConfig {
+            gravity: cgmath::vec2(0.0, -1000.0),
+            wall_friction: 1.0,
+            ..sticky()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        for row in 0..20 {
+            for column in 0..10 {
+                let x = 140.0 + 2.0 * column as f32 + (row % 2) as f32;
+                let y = 1.0 + 1.8 * row as f32;
+                let grain = particle(x, y).with_friction(0.5).with_cohesion(cohesion);
+                solver.add(grain);
+            }
+        }
+        run(&mut solver, 180);
+        solver
+            .get_objects()
+            .iter()
+            .map(|object| object.position.y)
+            .fold(0.0, f32::max)
+    };
+    let dry = settle(0.0);
+    let wet = settle(3000.0);
+    assert!(wet > dry + 5.0, "wet {wet}, dry {dry}");
+}
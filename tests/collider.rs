@@ -0,0 +1,104 @@
+use cgmath::vec2;
+use physics_engine::{Collider, SimulationConfig, Solver, Vertex};
+
+use common::{particle, run, DT};
+
+mod common;
+
+fn drop_on(collider: Collider, start: cgmath::Vector2<f32>) -> Solver {
+    drop_object_on(collider, particle(start.x, start.y))
+}
+
+fn drop_object_on(collider: Collider, object: Vertex) -> Solver {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    solver.add_collider(collider);
+    solver.add(object);
+    run(&mut solver, 120);
+    solver
+}
+
+#[test]
+fn particle_rests_on_segment() {
+    let solver = drop_on(
+        Collider::segment(vec2(100.0, 100.0), vec2(200.0, 100.0)),
+        vec2(150.0, 150.0),
+    );
+    let object = &solver.get_objects()[0];
+    assert!(
+        (object.position.y - 101.0).abs() < 0.1,
+        "{:?}",
+        object.position
+    );
+    assert!((object.position.x - 150.0).abs() < 0.01);
+}
+
+#[test]
+fn particle_rests_on_box() {
+    let solver = drop_on(
+        Collider::aabb(vec2(100.0, 50.0), vec2(200.0, 100.0)),
+        vec2(150.0, 150.0),
+    );
+    let object = &solver.get_objects()[0];
+    assert!(
+        (object.position.y - 101.0).abs() < 0.1,
+        "{:?}",
+        object.position
+    );
+}
+
+#[test]
+fn friction_holds_particle_on_ramp() {
+    let ramp = |friction| {
+        let collider =
+            Collider::rotated_box(vec2(150.0, 100.0), vec2(60.0, 5.0), 0.3).with_friction(friction);
+        let object = particle(150.0, 107.0).with_friction(friction);
+        let solver = drop_object_on(collider, object);
+        solver.get_objects()[0].position.x
+    };
+    let sticky = ramp(1.0);
+    let slippery = ramp(0.0);
+    assert!(
+        (sticky - 150.0).abs() < 5.0,
+        "sticky particle moved to {sticky}"
+    );
+    assert!(slippery < 100.0, "slippery particle stopped at {slippery}");
+}
+
+#[test]
+fn circle_deflects_falling_particle() {
+    let solver = drop_on(
+        Collider::circle(vec2(150.0, 100.0), 20.0),
+        vec2(152.0, 200.0),
+    );
+    let object = &solver.get_objects()[0];
+    assert!(object.position.x > 170.0, "{:?}", object.position);
+}
+
+#[test]
+fn polygon_signed_distance() {
+    let collider = Collider::polygon(vec![
+        vec2(0.0, 0.0),
+        vec2(0.0, 10.0),
+        vec2(10.0, 10.0),
+        vec2(10.0, 0.0),
+    ]);
+    let (distance, normal) = collider.shape.signed_distance(vec2(5.0, 8.0));
+    assert_eq!(distance, -2.0);
+    assert_eq!(normal, vec2(0.0, 1.0));
+    let (distance, normal) = collider.shape.signed_distance(vec2(13.0, 14.0));
+    assert_eq!(distance, 5.0);
+    assert_eq!(normal, vec2(0.6, 0.8));
+}
+
+#[test]
+fn far_particles_skip_colliders() {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    for i in 0..100 {
+        let x = i as f32 * 3.0;
+        solver.add_collider(Collider::circle(vec2(x, 20.0), 1.0));
+    }
+    solver.add(particle(150.0, 250.0));
+    solver.update(DT);
+    assert_eq!(solver.get_colliders().len(), 100);
+    assert!(solver.get_objects()[0].position.y < 250.0);
+}
@@ -0,0 +1,25 @@
+// Fixtures shared by the integration tests, each test crate only uses some of them.
+#![allow(dead_code)]
+
+use physics_engine::{SimulationConfig, Solver, Vertex};
+
+pub const DT: f32 = 1.0 / 60.0;
+
+pub fn run(solver: &mut Solver, updates: usize) {
+    for _ in 0..updates {
+        solver.update(DT);
+    }
+}
+
+/// The default world without gravity.
+pub fn floating() -> SimulationConfig {
+    SimulationConfig {
+        gravity: cgmath::vec2(0.0, 0.0),
+        ..SimulationConfig::default()
+    }
+}
+
+/// A white particle of the default radius.
+pub fn particle(x: f32, y: f32) -> Vertex {
+    Vertex::new(cgmath::vec2(x, y), cgmath::vec3(1.0, 1.0, 1.0))
+}
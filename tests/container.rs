@@ -0,0 +1,100 @@
+use cgmath::{vec2, InnerSpace, Vector2};
+use physics_engine::{Container, SimulationConfig, Solver};
+
+use common::{particle, run};
+
+mod common;
+
+fn scatter(container: Container) -> Solver {
+    scatter_with(SimulationConfig::default(), container)
+}
+
+fn scatter_with(config: SimulationConfig, container: Container) -> Solver {
+    let mut solver = Solver::new(config, Vec::new());
+    solver.set_container(container);
+    for i in 0..10 {
+        for j in 0..10 {
+            let (x, y) = (120.0 + i as f32 * 6.0, 120.0 + j as f32 * 6.0);
+            solver.add(particle(x, y).with_friction(1.0));
+        }
+    }
+    solver
+}
+
+fn max_distance(solver: &Solver, center: Vector2<f32>) -> f32 {
+    solver
+        .get_objects()
+        .iter()
+        .map(|object| (object.position - center).magnitude())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn circle_keeps_particles_inside() {
+    let center = vec2(150.0, 150.0);
+    let mut solver = scatter(Container::Circle {
+        center,
+        radius: 50.0,
+    });
+    run(&mut solver, 120);
+    assert!(max_distance(&solver, center) < 49.1);
+    let lowest = solver
+        .get_objects()
+        .iter()
+        .map(|object| object.position.y)
+        .fold(f32::MAX, f32::min);
+    assert!(lowest < 102.0, "particles did not settle: {lowest}");
+}
+
+#[test]
+fn sdf_matches_builtin_circle() {
+    let center = vec2(150.0, 150.0);
+    let mut solver = scatter(Container::sdf(move |point| {
+        (point - center).magnitude() - 50.0
+    }));
+    run(&mut solver, 120);
+    assert!(max_distance(&solver, center) < 49.1);
+}
+
+#[test]
+fn capsule_keeps_particles_inside() {
+    let mut solver = scatter(Container::Capsule {
+        a: vec2(100.0, 150.0),
+        b: vec2(200.0, 150.0),
+        radius: 30.0,
+    });
+    run(&mut solver, 120);
+    for object in solver.get_objects() {
+        let x = object.position.x.clamp(100.0, 200.0);
+        let distance = (object.position - vec2(x, 150.0)).magnitude();
+        assert!(distance < 29.1, "{:?}", object.position);
+    }
+}
+
+fn rotating_box_pile(angular_velocity: f32) -> f32 {
+    let config = SimulationConfig {
+        wall_friction: 1.0,
+        ..SimulationConfig::default()
+    };
+    let center = vec2(150.0, 150.0);
+    let mut solver = scatter_with(
+        config,
+        Container::RotatingBox {
+            center,
+            half_extents: vec2(60.0, 60.0),
+            angle: 0.0,
+            angular_velocity,
+        },
+    );
+    run(&mut solver, 60);
+    assert!(max_distance(&solver, center) < 60.0 * 2f32.sqrt());
+    let objects = solver.get_objects();
+    objects.iter().map(|object| object.position.x).sum::<f32>() / objects.len() as f32
+}
+
+#[test]
+fn rotating_box_drags_particles_along() {
+    let still = rotating_box_pile(0.0);
+    let spinning = rotating_box_pile(0.5);
+    assert!(spinning > still + 5.0, "{still} {spinning}");
+}
@@ -0,0 +1,173 @@
+use cgmath::InnerSpace;
+
+use physics_engine::{ColorSource, Emitter, EmitterShape, Schedule, SimulationConfig, Solver};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+#[test]
+fn rate_carries_fractions_between_updates() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let point = EmitterShape::Point(cgmath::vec2(150.0, 150.0));
+    solver.add_emitter(Emitter::new(point, 90.0).with_max_count(1000));
+    run(&mut solver, 60);
+    assert_eq!(solver.get_objects().len(), 90);
+    assert_eq!(solver.get_emitters()[0].emitted(), 90);
+}
+
+#[test]
+fn max_count_finishes_the_emitter() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let disk = EmitterShape::Disk {
+        center: cgmath::vec2(150.0, 150.0),
+        radius: 50.0,
+    };
+    solver.add_emitter(Emitter::new(disk, 600.0).with_max_count(25));
+    solver.update(DT);
+    assert!(!solver.get_emitters()[0].is_finished());
+    run(&mut solver, 9);
+    assert_eq!(solver.get_objects().len(), 25);
+    // Finished emitters are dropped.
+    assert!(solver.get_emitters().is_empty());
+
+    solver.add_emitter(Emitter::burst(disk, 40));
+    solver.update(DT);
+    assert_eq!(solver.get_objects().len(), 65);
+    assert!(solver.get_emitters().is_empty());
+}
+
+#[test]
+fn emitter_handles_survive_finished_emitters() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let point = EmitterShape::Point(cgmath::vec2(150.0, 150.0));
+    let burst = solver.add_emitter(Emitter::burst(point, 5));
+    let stream = solver.add_emitter(Emitter::new(point, 60.0));
+    solver.update(DT);
+    assert!(solver.emitter(burst).is_none());
+    assert!(solver.emitter_mut(burst).is_none());
+    assert_eq!(solver.emitter(stream).unwrap().emitted(), 1);
+
+    // The slot of the burst is reused without reviving its handle.
+    let next = solver.add_emitter(Emitter::burst(point, 5));
+    assert!(solver.emitter(burst).is_none());
+    solver.emitter_mut(stream).unwrap().enabled = false;
+    solver.update(DT);
+    assert!(solver.emitter(next).is_none());
+    assert_eq!(solver.emitter(stream).unwrap().emitted(), 1);
+}
+
+#[test]
+fn unseeded_emitters_spread_differently() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let area = EmitterShape::Area {
+        min: cgmath::vec2(100.0, 100.0),
+        max: cgmath::vec2(200.0, 200.0),
+    };
+    let dust = particle(0.0, 0.0).with_radius(0.01);
+    solver.add_emitter(Emitter::burst(area, 10).with_template(dust.clone()));
+    solver.add_emitter(Emitter::burst(area, 10).with_template(dust));
+    solver.update(DT);
+    let objects = solver.get_objects();
+    assert_ne!(objects[0].position, objects[10].position);
+}
+
+#[test]
+fn unbounded_rates_are_capped_per_update() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let dust = particle(0.0, 0.0).with_radius(0.01);
+    let area = EmitterShape::Area {
+        min: cgmath::vec2(10.0, 10.0),
+        max: cgmath::vec2(290.0, 290.0),
+    };
+    solver.add_emitter(Emitter::new(area, f32::INFINITY).with_template(dust));
+    run(&mut solver, 2);
+    assert_eq!(solver.get_objects().len(), 2 * Emitter::MAX_PER_UPDATE);
+}
+
+#[test]
+fn particles_start_inside_the_shape() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let color = cgmath::vec3(0.5, 0.25, 1.0);
+    // Small enough to barely ever touch each other.
+    let dust = particle(0.0, 0.0).with_radius(0.01);
+    let area = EmitterShape::Area {
+        min: cgmath::vec2(20.0, 40.0),
+        max: cgmath::vec2(60.0, 50.0),
+    };
+    let emitter = Emitter::burst(area, 50)
+        .with_template(dust.clone())
+        .with_color(ColorSource::Fixed(color));
+    solver.add_emitter(emitter);
+    let line = EmitterShape::Line {
+        a: cgmath::vec2(200.0, 100.0),
+        b: cgmath::vec2(200.0, 200.0),
+    };
+    solver.add_emitter(Emitter::burst(line, 50).with_template(dust).with_seed(7));
+    solver.update(DT);
+
+    let (boxed, lined) = solver.get_objects().split_at(50);
+    for object in boxed {
+        assert!((20.0..=60.0).contains(&object.position.x));
+        assert!((40.0..=50.0).contains(&object.position.y));
+        assert_eq!(object.color, color);
+    }
+    for object in lined {
+        assert!((object.position.x - 200.0).abs() < 1e-3);
+        assert!((100.0..=200.0).contains(&object.position.y));
+    }
+}
+
+#[test]
+fn launch_directions_stay_within_the_spread() {
+    let config = SimulationConfig {
+        sort_interval: 0,
+        ..floating()
+    };
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut solver = Solver::new(config, Vec::new());
+    let disk = EmitterShape::Disk {
+        center: cgmath::vec2(150.0, 150.0),
+        radius: 100.0,
+    };
+    let emitter = Emitter::new(disk, 60.0)
+        .with_velocity(cgmath::vec2(0.0, 20.0))
+        .with_spread(0.3);
+    solver.add_emitter(emitter);
+    run(&mut solver, 30);
+
+    for object in solver.get_objects() {
+        let velocity = object.velocity(sub_dt);
+        assert!((velocity.magnitude() - 20.0).abs() < 0.5);
+        assert!(velocity.x.atan2(velocity.y).abs() <= 0.31);
+    }
+}
+
+#[test]
+fn schedules_switch_emission_on_and_off() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    let point = EmitterShape::Point(cgmath::vec2(100.0, 100.0));
+    let pulse = Schedule::Pulse {
+        on: 0.25,
+        off: 0.25,
+    };
+    let pulsing = solver.add_emitter(Emitter::new(point, 60.0).with_schedule(pulse));
+    let window = Schedule::Between {
+        start: 0.5,
+        end: 1.0,
+    };
+    solver.add_emitter(Emitter::new(point, 60.0).with_schedule(window));
+    run(&mut solver, 60);
+
+    // Half of every second for both.
+    let emitted: Vec<usize> = solver.get_emitters().iter().map(Emitter::emitted).collect();
+    assert!(
+        emitted.iter().all(|&count| (29..=31).contains(&count)),
+        "{emitted:?}"
+    );
+
+    solver.emitter_mut(pulsing).unwrap().enabled = false;
+    run(&mut solver, 60);
+    assert_eq!(solver.get_emitters()[0].emitted(), emitted[0]);
+    assert_eq!(solver.get_emitters()[1].emitted(), emitted[1]);
+}
@@ -0,0 +1,53 @@
+use physics_engine::{Engine, SimulationConfig};
+
+use common::particle;
+
+mod common;
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        time_step: 0.01,
+        max_steps_per_frame: 4,
+        ..SimulationConfig::default()
+    }
+}
+
+#[test]
+fn fixed_steps_follow_elapsed_time() {
+    let mut engine = Engine::new(config());
+    engine.solver.add(particle(150.0, 150.0));
+
+    assert_eq!(engine.advance(0.005), 0);
+    assert!((engine.alpha() - 0.5).abs() < 1e-4);
+    assert_eq!(engine.advance(0.03), 3);
+    assert!((engine.alpha() - 0.5).abs() < 1e-3);
+
+    // The particle falls, so the blended position lies above the current one.
+    let position = engine.interpolated_positions().next().unwrap();
+    let current = engine.solver.get_objects()[0].position;
+    assert!(position.y > current.y && position.y < 150.0);
+}
+
+#[test]
+fn catch_up_is_capped() {
+    let mut engine = Engine::new(config());
+    assert_eq!(engine.advance(1.0), 4);
+    assert!(engine.alpha() < 1.0);
+    assert_eq!(engine.advance(0.0), 0);
+}
+
+#[test]
+fn bursts_do_not_pile_up_emitters() {
+    let mut engine = Engine::new(config());
+    for _ in 0..3 {
+        engine.add_at_position(100.0, 150.0);
+        engine.add_water(150.0, 150.0);
+        engine.add_mud(200.0, 150.0);
+        engine.advance(0.01);
+    }
+    assert_eq!(engine.solver.get_objects().len(), 900);
+    // Only the stream is left, and it still toggles.
+    assert_eq!(engine.solver.get_emitters().len(), 1);
+    engine.toggle_add_objects();
+    assert!(engine.solver.get_emitters()[0].enabled);
+}
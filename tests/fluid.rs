@@ -0,0 +1,203 @@
+use cgmath::InnerSpace;
+
+use physics_engine::{Fluid, Phase, SimulationConfig, Solver, Vertex};
+
+use common::{floating, run, DT};
+
+mod common;
+
+fn particle(x: f32, y: f32, phase: Phase) -> Vertex {
+    common::particle(x, y).with_phase(phase)
+}
+
+/// A column of particles against the left wall, rows offset like packed grains.
+fn column(solver: &mut Solver, phase: Phase, friction: f32) {
+    for row in 0..20 {
+        for column in 0..10 {
+            let x = 1.0 + 2.0 * column as f32 + (row % 2) as f32;
+            let y = 1.0 + 1.8 * row as f32;
+            solver.add(particle(x, y, phase).with_friction(friction));
+        }
+    }
+}
+
+/// A hexagonally packed square of small particles `spacing` apart, `phase` picks the phase
+/// of every particle by its index.
+fn cluster(solver: &mut Solver, spacing: f32, phase: impl Fn(usize) -> Phase) {
+    for row in 0..10 {
+        for column in 0..10 {
+            let x = 140.0 + spacing * (column as f32 + 0.5 * (row % 2) as f32);
+            let y = 140.0 + spacing * row as f32 * 3f32.sqrt() / 2.0;
+            let particle = particle(x, y, phase(row * 10 + column)).with_radius(0.1);
+            solver.add(particle);
+        }
+    }
+}
+
+/// Average distance from every particle to its closest neighbour.
+fn mean_spacing(solver: &Solver) -> f32 {
+    let objects = solver.get_objects();
+    let closest = |object: &Vertex| {
+        objects
+            .iter()
+            .map(|other| (other.position - object.position).magnitude())
+            .filter(|&distance| distance > 0.0)
+            .fold(f32::MAX, f32::min)
+    };
+    objects.iter().map(closest).sum::<f32>() / objects.len() as f32
+}
+
+fn extent(solver: &Solver) -> (cgmath::Vector2<f32>, cgmath::Vector2<f32>) {
+    solver.get_objects().iter().fold(
+        (
+            cgmath::vec2(f32::MAX, f32::MAX),
+            cgmath::vec2(f32::MIN, f32::MIN),
+        ),
+        |(min, max), object| {
+            let p = object.position;
+            (
+                cgmath::vec2(min.x.min(p.x), min.y.min(p.y)),
+                cgmath::vec2(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    )
+}
+
+#[test]
+fn packed_density_matches_the_default_rest_density() {
+    let fluid = Fluid::new(1.0);
+    assert_eq!(fluid.smoothing_radius, 4.0);
+    assert_eq!(fluid.rest_density, Fluid::packed_density(2.2, 4.0));
+    // Touching particles are denser than the fluid at rest.
+    assert!(Fluid::packed_density(2.0, 4.0) > fluid.rest_density);
+}
+
+#[test]
+fn fluid_columns_collapse_further_than_sand() {
+    let settle = |phase| {
+        // Rough walls, or even sand slides along the floor.
+        let config = SimulationConfig {
+            wall_friction: 1.0,
+            ..SimulationConfig::default()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        column(&mut solver, phase, 0.5);
+        run(&mut solver, 240);
+        let objects = solver.get_objects();
+        let count = objects.len() as f32;
+        let mean_x = objects.iter().map(|object| object.position.x).sum::<f32>() / count;
+        let mean_y = objects.iter().map(|object| object.position.y).sum::<f32>() / count;
+        (mean_x, mean_y)
+    };
+    let sand = settle(Phase::Solid);
+    let water = settle(Phase::Fluid);
+    assert!(water.0 > sand.0 + 10.0, "water {water:?}, sand {sand:?}");
+    assert!(water.1 < sand.1 - 1.0, "water {water:?}, sand {sand:?}");
+}
+
+#[test]
+fn compressed_fluid_relaxes_towards_rest_density() {
+    let relax = |phase| {
+        // Heavy damping keeps the pressure from splashing the cluster apart, so it stops
+        // where the density constraint lets go.
+        let config = SimulationConfig {
+            linear_damping: 200.0,
+            ..floating()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        cluster(&mut solver, 1.5, |_| phase);
+        run(&mut solver, 30);
+        mean_spacing(&solver)
+    };
+    // The default fluid rests with particles 2.2 apart, the small radius keeps them from
+    // colliding so only the density constraint can spread them.
+    let solid = relax(Phase::Solid);
+    let fluid = relax(Phase::Fluid);
+    assert!((solid - 1.5).abs() < 1e-4, "{solid}");
+    assert!(fluid > 2.0 && fluid < 2.5, "{fluid}");
+}
+
+#[test]
+fn viscosity_damps_relative_motion() {
+    let spread = |viscosity| {
+        let config = SimulationConfig {
+            fluid: Fluid {
+                viscosity,
+                ..Fluid::new(1.0)
+            },
+            ..floating()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        let left = solver.add_with_velocity(
+            particle(149.0, 150.0, Phase::Fluid),
+            cgmath::vec2(0.0, -10.0),
+        );
+        let right = solver.add_with_velocity(
+            particle(151.5, 150.0, Phase::Fluid),
+            cgmath::vec2(0.0, 10.0),
+        );
+        run(&mut solver, 10);
+        (solver.velocity(right).unwrap() - solver.velocity(left).unwrap()).magnitude()
+    };
+    let free = spread(0.0);
+    assert!((free - 20.0).abs() < 0.5, "{free}");
+    assert!(spread(0.2) < 0.5 * free);
+}
+
+#[test]
+fn surface_tension_pulls_loose_drops_together() {
+    let size = |surface_tension| {
+        let config = SimulationConfig {
+            fluid: Fluid {
+                surface_tension,
+                ..Fluid::new(1.0)
+            },
+            ..floating()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        for i in 0..8 {
+            for j in 0..8 {
+                let x = 140.0 + 2.8 * i as f32;
+                let y = 140.0 + 2.8 * j as f32;
+                solver.add(particle(x, y, Phase::Fluid));
+            }
+        }
+        run(&mut solver, 60);
+        let (min, max) = extent(&solver);
+        (max - min).magnitude()
+    };
+    let loose = size(0.0);
+    let held = size(0.5);
+    assert!(held < 0.9 * loose, "held {held}, loose {loose}");
+}
+
+#[test]
+fn solid_particles_ignore_the_fluid() {
+    // A single sub-step, so the fluid cannot run into the solids after it moved.
+    let config = SimulationConfig {
+        sub_steps: 1,
+        ..floating()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    // Every third particle of a compressed fluid cluster is solid.
+    let phase = |index| {
+        if index % 3 == 0 {
+            Phase::Solid
+        } else {
+            Phase::Fluid
+        }
+    };
+    cluster(&mut solver, 1.5, phase);
+    let start: Vec<_> = solver
+        .get_objects()
+        .iter()
+        .map(|object| object.position)
+        .collect();
+    solver.update(DT);
+    for (object, start) in solver.get_objects().iter().zip(start) {
+        match object.phase {
+            Phase::Solid => assert_eq!(object.position, start),
+            Phase::Fluid => assert_ne!(object.position, start),
+        }
+    }
+}
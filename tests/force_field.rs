@@ -0,0 +1,154 @@
+use cgmath::InnerSpace;
+
+use physics_engine::{Falloff, ForceField, Periodic, SimulationConfig, Solver};
+
+use common::{floating, particle, DT};
+
+mod common;
+
+/// Velocity a resting particle at `position` picks up in one update.
+fn kick(config: SimulationConfig, field: ForceField, position: (f32, f32)) -> cgmath::Vector2<f32> {
+    let mut solver = Solver::new(config, Vec::new());
+    solver.add_force_field(field);
+    let id = solver.add(particle(position.0, position.1));
+    solver.update(DT);
+    solver.velocity(id).unwrap()
+}
+
+fn center() -> cgmath::Vector2<f32> {
+    cgmath::vec2(150.0, 150.0)
+}
+
+#[test]
+fn attractors_pull_and_repulsors_push_within_their_radius() {
+    let attractor = || ForceField::attractor(center(), 600.0, 50.0, Falloff::None);
+    let pulled = kick(floating(), attractor(), (120.0, 150.0));
+    assert!(
+        (pulled - cgmath::vec2(10.0, 0.0)).magnitude() < 0.1,
+        "{pulled:?}"
+    );
+    assert_eq!(
+        kick(floating(), attractor(), (90.0, 150.0)),
+        cgmath::vec2(0.0, 0.0)
+    );
+
+    let repulsor = ForceField::repulsor(center(), 600.0, 50.0, Falloff::None);
+    let pushed = kick(floating(), repulsor, (150.0, 170.0));
+    assert!(
+        (pushed - cgmath::vec2(0.0, 10.0)).magnitude() < 0.1,
+        "{pushed:?}"
+    );
+}
+
+#[test]
+fn falloff_weakens_fields_with_distance() {
+    let linear = |x| {
+        let field = ForceField::attractor(center(), 600.0, 40.0, Falloff::Linear);
+        kick(floating(), field, (x, 150.0)).x
+    };
+    assert!((linear(140.0) - 7.5).abs() < 0.1);
+    assert!((linear(120.0) - 2.5).abs() < 0.1);
+
+    let inverse_square = |x| {
+        let field = ForceField::attractor(
+            center(),
+            600.0,
+            1000.0,
+            Falloff::InverseSquare { core: 10.0 },
+        );
+        kick(floating(), field, (x, 150.0)).x
+    };
+    assert!((inverse_square(145.0) - 10.0).abs() < 0.1);
+    assert!((inverse_square(130.0) - 2.5).abs() < 0.1);
+    assert!((inverse_square(110.0) - 0.625).abs() < 0.05);
+}
+
+#[test]
+fn round_fields_reach_across_periodic_axes() {
+    let config = SimulationConfig {
+        periodic: Periodic { x: true, y: false },
+        ..floating()
+    };
+    let field = ForceField::attractor(cgmath::vec2(5.0, 150.0), 600.0, 30.0, Falloff::None);
+    let pulled = kick(config, field, (290.0, 150.0));
+    assert!((pulled.x - 10.0).abs() < 0.1, "{pulled:?}");
+}
+
+#[test]
+fn vortices_push_sideways() {
+    let vortex = || ForceField::vortex(center(), 600.0, 50.0, Falloff::None);
+    let right = kick(floating(), vortex(), (170.0, 150.0));
+    assert!(
+        (right - cgmath::vec2(0.0, 10.0)).magnitude() < 0.1,
+        "{right:?}"
+    );
+    let above = kick(floating(), vortex(), (150.0, 170.0));
+    assert!(
+        (above - cgmath::vec2(-10.0, 0.0)).magnitude() < 0.1,
+        "{above:?}"
+    );
+}
+
+#[test]
+fn wind_only_blows_inside_its_zone() {
+    let wind = || {
+        ForceField::wind(
+            cgmath::vec2(100.0, 100.0),
+            cgmath::vec2(200.0, 120.0),
+            cgmath::vec2(300.0, 60.0),
+        )
+    };
+    let blown = kick(floating(), wind(), (150.0, 110.0));
+    assert!((blown - cgmath::vec2(5.0, 1.0)).magnitude() < 0.05);
+    assert_eq!(
+        kick(floating(), wind(), (150.0, 130.0)),
+        cgmath::vec2(0.0, 0.0)
+    );
+}
+
+#[test]
+fn turbulence_swirls_without_a_net_drift() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    solver.add_force_field(ForceField::turbulence(300.0, 20.0, 0.5));
+    for i in 0..40 {
+        for j in 0..40 {
+            let (x, y) = (50.0 + i as f32 * 5.0, 50.0 + j as f32 * 5.0);
+            solver.add(particle(x, y).with_radius(0.1));
+        }
+    }
+    solver.update(DT);
+
+    let velocities: Vec<_> = solver
+        .ids()
+        .iter()
+        .map(|&id| solver.velocity(id).unwrap())
+        .collect();
+    let count = velocities.len() as f32;
+    let mean = velocities
+        .iter()
+        .fold(cgmath::vec2(0.0, 0.0), |sum, &v| sum + v)
+        / count;
+    let speed = velocities.iter().map(|v| v.magnitude()).sum::<f32>() / count;
+    assert!(speed > 1.0 && speed < 20.0, "mean speed {speed}");
+    assert!(
+        mean.magnitude() < 0.2 * speed,
+        "drift {mean:?} at speed {speed}"
+    );
+}
+
+#[test]
+fn custom_fields_and_fields_add_up() {
+    let mut solver = Solver::new(floating(), Vec::new());
+    solver.add_force_field(ForceField::custom(|object, _| {
+        cgmath::vec2(0.0, -object.mass * 60.0)
+    }));
+    solver.add_force_field(ForceField::custom(|_, time| {
+        cgmath::vec2(if time < 1.0 { 120.0 } else { 0.0 }, 0.0)
+    }));
+    let light = solver.add(particle(100.0, 150.0));
+    let heavy = solver.add(particle(200.0, 150.0).with_mass(2.0));
+    solver.update(DT);
+    // Accelerations, so the force of the first field grows with the mass.
+    assert!((solver.velocity(light).unwrap() - cgmath::vec2(2.0, -1.0)).magnitude() < 0.05);
+    assert!((solver.velocity(heavy).unwrap() - cgmath::vec2(2.0, -2.0)).magnitude() < 0.05);
+}
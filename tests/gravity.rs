@@ -0,0 +1,149 @@
+use cgmath::InnerSpace;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use physics_engine::{
+    BroadphaseKind, Integrator, Motion, NBody, PointGravity, SimulationConfig, Solver, Vertex,
+};
+
+use common::{floating, run, DT};
+
+mod common;
+
+/// A large, sparse world, which sweep-and-prune handles better than the dense grid.
+fn weightless() -> SimulationConfig {
+    SimulationConfig {
+        world_size: cgmath::vec2(1000.0, 1000.0),
+        broadphase: BroadphaseKind::SweepAndPrune,
+        ..floating()
+    }
+}
+
+/// Small enough that the particles barely ever collide.
+fn speck(x: f32, y: f32) -> Vertex {
+    common::particle(x, y).with_radius(0.1)
+}
+
+#[test]
+fn point_gravity_replaces_uniform_gravity() {
+    let config = SimulationConfig {
+        gravity: cgmath::vec2(0.0, -1000.0),
+        point_gravity: Some(PointGravity {
+            softening: 0.0,
+            ..PointGravity::new(cgmath::vec2(500.0, 500.0), 1.0e6)
+        }),
+        ..weightless()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    let id = solver.add(speck(400.0, 500.0));
+    solver.update(DT);
+    // 1e6 / 100^2 towards the center, nothing downwards.
+    let velocity = solver.velocity(id).unwrap();
+    assert!(
+        (velocity - cgmath::vec2(100.0 * DT, 0.0)).magnitude() < 0.05,
+        "{velocity:?}"
+    );
+}
+
+#[test]
+fn point_gravity_keeps_circular_orbits() {
+    let center = cgmath::vec2(500.0, 500.0);
+    let strength = 1.0e6;
+    let config = SimulationConfig {
+        point_gravity: Some(PointGravity::new(center, strength)),
+        ..weightless()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    let radius: f32 = 200.0;
+    let speed = (strength / radius).sqrt();
+    // An explicit velocity keeps rounding from piling up over thousands of sub-steps.
+    let moon = speck(500.0 + radius, 500.0).with_integrator(Integrator::VelocityVerlet);
+    let id = solver.add_with_velocity(moon, cgmath::vec2(0.0, speed));
+    // A bit more than one revolution.
+    let period = std::f32::consts::TAU * radius / speed;
+    let updates = (1.2 * period / DT) as usize;
+    for _ in 0..updates {
+        solver.update(DT);
+        let distance = (solver.get(id).unwrap().position - center).magnitude();
+        assert!(
+            (distance - radius).abs() < 0.01 * radius,
+            "drifted to {distance}"
+        );
+    }
+}
+
+#[test]
+fn n_body_pulls_conserve_momentum() {
+    let config = SimulationConfig {
+        n_body: Some(NBody::new(1.0e6)),
+        ..weightless()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    // Close to the origin, where positions resolve the small velocities best.
+    let light = solver.add(speck(20.0, 50.0));
+    let heavy = solver.add(speck(120.0, 50.0).with_mass(3.0));
+    solver.update(DT);
+    let light = solver.velocity(light).unwrap();
+    let heavy = solver.velocity(heavy).unwrap();
+    assert!(light.x > 0.0 && heavy.x < 0.0);
+    assert!((light.x + 3.0 * heavy.x).abs() < 0.02 * light.x);
+    // 1e6 * 3 / 100^2 for the light particle.
+    assert!((light.x - 300.0 * DT).abs() < 0.02 * light.x, "{light:?}");
+}
+
+#[test]
+fn pinned_particles_attract_without_moving() {
+    let config = SimulationConfig {
+        n_body: Some(NBody::new(1000.0)),
+        ..weightless()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    let sun = solver.add(
+        speck(500.0, 500.0)
+            .with_mass(1000.0)
+            .with_motion(Motion::Pinned),
+    );
+    let moon = solver.add(speck(500.0, 400.0));
+    run(&mut solver, 10);
+    assert_eq!(
+        solver.get(sun).unwrap().position,
+        cgmath::vec2(500.0, 500.0)
+    );
+    assert!(solver.get(moon).unwrap().position.y > 400.5);
+}
+
+#[test]
+fn barnes_hut_matches_exact_sums() {
+    let cloud = |theta: f32| {
+        let config = SimulationConfig {
+            n_body: Some(NBody {
+                theta,
+                ..NBody::new(1000.0)
+            }),
+            sort_interval: 0,
+            ..weightless()
+        };
+        let mut solver = Solver::new(config, Vec::new());
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..600 {
+            let position = (rng.gen_range(100.0..900.0), rng.gen_range(100.0..900.0));
+            let mass = rng.gen_range(0.5..2.0);
+            solver.add(speck(position.0, position.1).with_mass(mass));
+        }
+        solver.update(DT);
+        solver
+            .ids()
+            .iter()
+            .map(|&id| solver.velocity(id).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let exact = cloud(0.0);
+    let approximate = cloud(0.5);
+    let error: f32 = exact
+        .iter()
+        .zip(approximate.iter())
+        .map(|(e, a)| (e - a).magnitude())
+        .sum();
+    let total: f32 = exact.iter().map(|e| e.magnitude()).sum();
+    assert!(error < 0.02 * total, "relative error {}", error / total);
+}
@@ -0,0 +1,217 @@
+use cgmath::{InnerSpace, Vector2};
+
+use physics_engine::{
+    Broadphase, BroadphaseKind, Container, DensestCell, Grid, HashGrid, Periodic, SimulationConfig,
+    Solver, Vertex,
+};
+
+use common::{floating, particle, DT};
+
+mod common;
+
+fn tank_config() -> SimulationConfig {
+    SimulationConfig {
+        world_size: cgmath::vec2(800.0, 200.0),
+        ..floating()
+    }
+}
+
+fn corners(config: &SimulationConfig) -> [Vector2<f32>; 4] {
+    let r = config.radius;
+    let size = config.world_size;
+    [
+        cgmath::vec2(r, r),
+        cgmath::vec2(size.x - r, r),
+        cgmath::vec2(r, size.y - r),
+        cgmath::vec2(size.x - r, size.y - r),
+    ]
+}
+
+#[test]
+fn grid_dimensions_follow_world_extents() {
+    let config = tank_config();
+    let grid = Grid::new(&config);
+    assert_eq!(grid.width(), 400);
+    assert_eq!(grid.height(), 100);
+}
+
+#[test]
+fn corner_objects_are_found_by_their_neighbours() {
+    let config = tank_config();
+    let mut grid = Grid::new(&config);
+    let corners = corners(&config);
+    grid.rebuild(corners.iter().copied());
+
+    for (id, corner) in corners.iter().enumerate() {
+        let (column, row) = grid.cell_coords(corner.x, corner.y).unwrap();
+        assert_eq!(grid.get_cell_objects(column, row), &[id]);
+
+        // Every neighbouring cell must see the corner object in its own neighbourhood.
+        for (n_column, n_row) in grid.neighbour_cells(column, row) {
+            let found: Vec<usize> = grid.get_neighbour_objects(n_column, n_row).collect();
+            assert_eq!(
+                found,
+                vec![id],
+                "corner {id} from cell ({n_column}, {n_row})"
+            );
+        }
+    }
+}
+
+#[test]
+fn neighbour_cells_are_clipped_at_the_borders() {
+    let config = tank_config();
+    let grid = Grid::new(&config);
+    assert_eq!(grid.neighbour_cells(0, 0).count(), 4);
+    assert_eq!(grid.neighbour_cells(399, 99).count(), 4);
+    assert_eq!(grid.neighbour_cells(399, 50).count(), 6);
+    assert_eq!(grid.neighbour_cells(200, 50).count(), 9);
+}
+
+#[test]
+fn objects_outside_the_world_are_ignored() {
+    let config = tank_config();
+    let mut grid = Grid::new(&config);
+    assert_eq!(grid.cell_coords(-1.0, 10.0), None);
+    assert_eq!(grid.cell_coords(10.0, 200.0), None);
+    grid.rebuild([cgmath::vec2(801.0, 10.0), cgmath::vec2(10.0, -1.0)].into_iter());
+    assert!((0..400).all(|column| grid.get_cell_objects(column, 5).is_empty()));
+    assert_eq!(grid.densest_cell().count, 0);
+}
+
+#[test]
+fn overlapping_corner_particles_are_separated() {
+    let config = tank_config();
+    let mut objects = Vec::new();
+    for corner in corners(&config) {
+        let inward = if corner.x > config.world_size.x / 2.0 {
+            -0.3
+        } else {
+            0.3
+        };
+        objects.push(particle(corner.x + inward, corner.y));
+        objects.push(particle(corner.x + inward * 2.0, corner.y));
+    }
+    let mut solver = Solver::new(config.clone(), objects);
+
+    solver.update(DT);
+
+    for pair in solver.get_objects().chunks(2) {
+        let distance = (pair[0].position - pair[1].position).magnitude();
+        assert!(distance > 1.0, "particles still overlap: {distance}");
+    }
+}
+
+#[test]
+fn cells_have_no_capacity_limit() {
+    let config = tank_config();
+    let mut grid = Grid::new(&config);
+    let pile = (0..50).map(|i| cgmath::vec2(10.1 + i as f32 * 0.01, 10.5));
+    grid.rebuild(pile.chain([cgmath::vec2(100.0, 100.0)]));
+
+    let (column, row) = grid.cell_coords(10.5, 10.5).unwrap();
+    let objects = grid.get_cell_objects(column, row);
+    assert_eq!(objects, (0..50).collect::<Vec<_>>().as_slice());
+    assert_eq!(
+        grid.densest_cell(),
+        DensestCell {
+            column,
+            row,
+            count: 50
+        }
+    );
+}
+
+#[test]
+fn every_particle_of_a_dense_pile_collides() {
+    let config = tank_config();
+    let center = cgmath::vec2(401.0, 101.0);
+    let objects: Vec<Vertex> = (0..16)
+        .map(|i| {
+            let angle = i as f32 / 16.0 * std::f32::consts::TAU;
+            particle(center.x + 0.5 * angle.cos(), center.y + 0.5 * angle.sin())
+        })
+        .collect();
+    let initial: Vec<f32> = objects
+        .iter()
+        .map(|object| (object.position - center).magnitude())
+        .collect();
+    let mut solver = Solver::new(config, objects);
+
+    solver.update(DT);
+
+    assert_eq!(solver.densest_cell().count, 16);
+    for (object, initial) in solver.get_objects().iter().zip(initial) {
+        let distance = (object.position - center).magnitude();
+        assert!(
+            distance > initial,
+            "particle was not pushed out of the pile"
+        );
+    }
+}
+
+#[test]
+fn periodic_neighbours_wrap_around() {
+    let mut config = tank_config();
+    config.cell_width = 3.0;
+    config.periodic = Periodic { x: true, y: false };
+    let grid = Grid::new(&config);
+    // 800 / 3 cells are stretched so that they tile the world exactly.
+    assert_eq!(grid.width(), 266);
+    assert_eq!(grid.cell_coords(799.9, 1.0), Some((265, 0)));
+    assert_eq!(grid.cell_coords(-0.5, 1.0), Some((265, 0)));
+    assert_eq!(grid.cell_coords(800.5, 1.0), Some((0, 0)));
+    assert_eq!(grid.cell_coords(1.0, -0.5), None);
+
+    let mut neighbours = grid.neighbour_cells(0, 0).collect::<Vec<_>>();
+    neighbours.sort();
+    assert_eq!(
+        neighbours,
+        [(0, 0), (0, 1), (1, 0), (1, 1), (265, 0), (265, 1)]
+    );
+}
+
+fn scattered_objects() -> Vec<Vertex> {
+    (0..500)
+        .map(|i| {
+            let x = (i * 37 % 101) as f32 * 1.3;
+            let y = (i * 53 % 97) as f32 * 1.1;
+            particle(x, y)
+        })
+        .collect()
+}
+
+#[test]
+fn hash_grid_finds_the_same_pairs_as_the_dense_grid() {
+    let config = tank_config();
+    let objects = scattered_objects();
+    let mut grid = Grid::new(&config);
+    let mut hash_grid = HashGrid::new(config.cell_width);
+    grid.build(&objects);
+    hash_grid.build(&objects);
+
+    let mut grid_pairs = Vec::new();
+    let mut hash_pairs = Vec::new();
+    grid.pairs(&mut grid_pairs);
+    hash_grid.pairs(&mut hash_pairs);
+    grid_pairs.sort();
+    hash_pairs.sort();
+    assert!(!grid_pairs.is_empty());
+    assert_eq!(grid_pairs, hash_pairs);
+}
+
+#[test]
+fn hash_grid_collides_at_negative_coordinates() {
+    let config = SimulationConfig {
+        broadphase: BroadphaseKind::HashGrid,
+        ..tank_config()
+    };
+    let objects = vec![particle(-5000.0, -20.0), particle(-5001.0, -20.0)];
+    let mut solver = Solver::new(config, objects);
+    solver.set_container(Container::Open);
+    solver.update(DT);
+    let objects = solver.get_objects();
+    let distance = (objects[0].position - objects[1].position).magnitude();
+    assert!(distance >= 2.0 - 1e-3, "{distance}");
+    assert!(objects[0].position.y == -20.0);
+}
@@ -0,0 +1,84 @@
+use cgmath::InnerSpace;
+
+use physics_engine::link::{chain, cloth, rope};
+use physics_engine::{Link, LinkParams, SimulationConfig, Solver, Vertex};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+fn template() -> Vertex {
+    particle(0.0, 0.0).with_radius(0.5)
+}
+
+#[test]
+fn rope_keeps_its_segment_length() {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    let ids = rope(
+        &mut solver,
+        cgmath::vec2(100.0, 100.0),
+        cgmath::vec2(120.0, 100.0),
+        20,
+        &template(),
+        LinkParams::default(),
+    );
+    assert_eq!(ids.len(), 21);
+    assert_eq!(solver.get_links().len(), 20);
+
+    run(&mut solver, 60);
+
+    for link in solver.get_links() {
+        let a = solver.get(link.a).unwrap().position;
+        let b = solver.get(link.b).unwrap().position;
+        let length = (a - b).magnitude();
+        assert!((length - 1.0).abs() < 0.05, "segment stretched to {length}");
+    }
+}
+
+#[test]
+fn overstretched_links_break() {
+    let config = floating();
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut solver = Solver::new(config, Vec::new());
+    let ids = chain(
+        &mut solver,
+        cgmath::vec2(100.0, 100.0),
+        cgmath::vec2(104.0, 100.0),
+        4,
+        &template(),
+        1.5,
+    );
+    let mut bullet = template().with_mass(1000.0);
+    bullet.position = cgmath::vec2(106.0, 100.0);
+    bullet.set_velocity(cgmath::vec2(500.0, 0.0), sub_dt);
+    let bullet = solver.add(bullet);
+    solver.add_link(Link::new(*ids.last().unwrap(), bullet, 2.0).with_break_ratio(1.5));
+
+    solver.update(DT);
+
+    let links = solver.get_links();
+    assert_eq!(links.len(), 4);
+    assert!(links.iter().all(|link| link.b != bullet));
+}
+
+#[test]
+fn cloth_links_horizontal_and_vertical_neighbours() {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    let ids = cloth(
+        &mut solver,
+        cgmath::vec2(100.0, 200.0),
+        5,
+        4,
+        1.0,
+        &template(),
+        LinkParams::default().with_stiffness(0.5),
+    );
+    assert_eq!(ids.len(), 20);
+    // 4 rows of 4 horizontal links and 3 rows of 5 vertical links.
+    assert_eq!(solver.get_links().len(), 16 + 15);
+    assert!(solver.get_links().iter().all(|link| link.stiffness == 0.5));
+    assert_eq!(
+        solver.get(ids[19]).unwrap().position,
+        cgmath::vec2(104.0, 197.0)
+    );
+}
@@ -0,0 +1,124 @@
+use physics_engine::{Link, Motion, Shape, SimulationConfig, Solver};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+fn row(count: usize) -> Solver {
+    let mut solver = Solver::new(floating(), Vec::new());
+    for i in 0..count {
+        solver.add(particle(10.0 + i as f32 * 5.0, 10.0));
+    }
+    solver
+}
+
+#[test]
+fn removal_keeps_handles_stable() {
+    let mut solver = row(5);
+    let ids = solver.ids().to_vec();
+    let removed = solver.remove(ids[1]).unwrap();
+    assert_eq!(removed.position.x, 15.0);
+    assert_eq!(solver.get_objects().len(), 4);
+    assert!(solver.get(ids[1]).is_none());
+    for (i, id) in ids.iter().enumerate().filter(|(i, _)| *i != 1) {
+        assert_eq!(solver.get(*id).unwrap().position.x, 10.0 + i as f32 * 5.0);
+        assert_eq!(solver.id(solver.index_of(*id).unwrap()), *id);
+    }
+    // The last object took the free place.
+    assert_eq!(solver.index_of(ids[4]), Some(1));
+}
+
+#[test]
+fn removed_handles_are_not_reused() {
+    let mut solver = row(2);
+    let old = solver.id(0);
+    solver.remove(old).unwrap();
+    let new = solver.add(particle(1.0, 1.0));
+    assert_eq!(new.slot(), old.slot());
+    assert_ne!(new, old);
+    assert!(solver.get(old).is_none());
+    assert!(solver.remove(old).is_none());
+}
+
+#[test]
+fn handles_follow_objects_when_storage_is_sorted() {
+    let config = SimulationConfig {
+        sort_interval: 1,
+        ..floating()
+    };
+    let mut solver = Solver::new(config, Vec::new());
+    // Added right to left, the sort reverses the storage.
+    let ids: Vec<_> = (0..5)
+        .map(|i| solver.add(particle(50.0 - i as f32 * 10.0, 10.0)))
+        .collect();
+    solver.add_link(Link::new(ids[0], ids[1], 10.0));
+    solver.update(DT);
+    assert_eq!(solver.index_of(ids[0]), Some(4));
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(solver.get(id).unwrap().position.x, 50.0 - i as f32 * 10.0);
+    }
+    solver.get_mut(ids[2]).unwrap().position.y = 20.0;
+    assert_eq!(solver.get_objects()[2].position.y, 20.0);
+    assert_eq!(solver.get_links()[0].a, ids[0]);
+}
+
+#[test]
+fn links_and_paths_follow_moved_objects() {
+    let mut solver = row(4);
+    let ids = solver.ids().to_vec();
+    solver.add_link(Link::new(ids[0], ids[1], 5.0));
+    solver.add_link(Link::new(ids[2], ids[3], 5.0));
+    solver.set_path(ids[3], |_| cgmath::vec2(25.0, 10.0));
+    solver.remove(ids[1]);
+    // The link to the removed object is gone, the other one still joins the same objects.
+    assert_eq!(solver.get_links().len(), 1);
+    let link = solver.get_links()[0];
+    assert_eq!((link.a, link.b), (ids[2], ids[3]));
+    solver.update(DT);
+    let moved = solver.get(ids[3]).unwrap();
+    assert_eq!(moved.motion, Motion::Kinematic);
+    assert_eq!(moved.position, cgmath::vec2(25.0, 10.0));
+}
+
+#[test]
+fn retain_removes_by_predicate() {
+    let mut solver = row(10);
+    let ids = solver.ids().to_vec();
+    solver.add_link(Link::new(ids[8], ids[9], 5.0));
+    solver.add_link(Link::new(ids[4], ids[5], 5.0));
+    let removed = solver.retain(|object| object.position.x < 30.0 || object.position.x > 45.0);
+    assert_eq!(removed, 4);
+    let mut xs: Vec<f32> = solver.get_objects().iter().map(|o| o.position.x).collect();
+    xs.sort_by(f32::total_cmp);
+    assert_eq!(xs, [10.0, 15.0, 20.0, 25.0, 50.0, 55.0]);
+    assert_eq!(solver.get_links().len(), 1);
+    let link = solver.get_links()[0];
+    assert_eq!(solver.get(link.a).unwrap().position.x, 50.0);
+    assert_eq!(solver.get(link.b).unwrap().position.x, 55.0);
+}
+
+#[test]
+fn expired_objects_despawn() {
+    let mut solver = row(0);
+    solver.add(particle(10.0, 10.0).with_lifetime(0.5));
+    solver.add(particle(20.0, 10.0));
+    run(&mut solver, 29);
+    assert_eq!(solver.get_objects().len(), 2);
+    solver.update(DT);
+    assert_eq!(solver.get_objects().len(), 1);
+    assert_eq!(solver.get_objects()[0].position.x, 20.0);
+}
+
+#[test]
+fn kill_zones_remove_touching_objects() {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    solver.add_kill_zone(Shape::Segment {
+        a: cgmath::vec2(0.0, 50.0),
+        b: cgmath::vec2(300.0, 50.0),
+    });
+    for i in 0..20 {
+        solver.add(particle(10.0 + i as f32 * 10.0, 100.0));
+    }
+    run(&mut solver, 60);
+    assert!(solver.get_objects().is_empty());
+}
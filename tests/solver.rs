@@ -0,0 +1,346 @@
+use cgmath::InnerSpace;
+
+use physics_engine::{ForceField, Integrator, Motion, Periodic, SimulationConfig, Solver, Walls};
+
+use common::{floating, particle, run, DT};
+
+mod common;
+
+fn pile(config: SimulationConfig) -> Solver {
+    let objects = (0..40)
+        .flat_map(|i| (0..40).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let jitter = (i * 7 + j * 13) % 10;
+            particle(
+                50.0 + i as f32 * 1.9 + jitter as f32 * 0.01,
+                100.0 + j as f32 * 1.9,
+            )
+        })
+        .collect();
+    Solver::new(config, objects)
+}
+
+#[test]
+fn deterministic_mode_does_not_depend_on_thread_count() {
+    let mut config = SimulationConfig {
+        deterministic: true,
+        ..SimulationConfig::default()
+    };
+    // `0` runs on the global pool.
+    let mut solvers: Vec<Solver> = [1, 3, 8, 0]
+        .into_iter()
+        .map(|threads| {
+            config.threads = threads;
+            pile(config.clone())
+        })
+        .collect();
+
+    for _ in 0..30 {
+        for solver in solvers.iter_mut() {
+            solver.update(DT);
+        }
+    }
+
+    let reference = solvers[0].get_objects();
+    for solver in &solvers[1..] {
+        assert_eq!(solver.get_objects(), reference);
+    }
+}
+
+#[test]
+fn collisions_use_radii_and_mass_ratio() {
+    let config = SimulationConfig {
+        sub_steps: 1,
+        ..floating()
+    };
+    let boulder = particle(150.0, 150.0).with_radius(5.0).with_mass(99.0);
+    let sand = particle(154.0, 150.0).with_radius(0.5).with_mass(1.0);
+    let mut solver = Solver::new(config, vec![boulder]);
+    solver.add(sand);
+    assert_eq!(solver.config().cell_width, 10.0);
+
+    solver.update(DT);
+
+    let objects = solver.get_objects();
+    let boulder_shift = 150.0 - objects[0].position.x;
+    let sand_shift = objects[1].position.x - 154.0;
+    assert!(objects[1].position.x - objects[0].position.x >= 5.5);
+    assert!((sand_shift / boulder_shift - 99.0).abs() < 0.1);
+}
+
+#[test]
+fn free_fall_matches_analytic_trajectory() {
+    let config = SimulationConfig {
+        world_size: cgmath::vec2(100.0, 700.0),
+        ..SimulationConfig::default()
+    };
+    let start = cgmath::vec2(50.0, 600.0);
+    let sub_dt = DT / config.sub_steps as f32;
+    for integrator in [
+        Integrator::PositionVerlet,
+        Integrator::VelocityVerlet,
+        Integrator::SemiImplicitEuler,
+    ] {
+        let vertex = particle(start.x, start.y).with_integrator(integrator);
+        let mut solver = Solver::new(config.clone(), vec![vertex]);
+        for step in 1..=60 {
+            solver.update(DT);
+            let t = step as f32 * DT;
+            let expected = start.y + 0.5 * config.gravity.y * t * t;
+            let actual = solver.get_objects()[0].position.y;
+            // Schemes starting from rest at a full step lag behind by g * t * sub_dt / 2.
+            let tolerance = 0.5 * config.gravity.y.abs() * t * sub_dt + 0.05;
+            assert!(
+                (actual - expected).abs() < tolerance,
+                "{integrator:?} at t = {t}: {actual} != {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn launch_speed_does_not_depend_on_sub_steps() {
+    let velocity = cgmath::vec2(30.0, 12.0);
+    // `0` runs a single sub-step, like `set_sub_steps(0)`.
+    for sub_steps in [0, 1, 4, 16] {
+        for integrator in [
+            Integrator::PositionVerlet,
+            Integrator::VelocityVerlet,
+            Integrator::SemiImplicitEuler,
+        ] {
+            let config = SimulationConfig {
+                sub_steps,
+                ..floating()
+            };
+            let mut solver = Solver::new(config, Vec::new());
+            let vertex = particle(100.0, 100.0).with_integrator(integrator);
+            let id = solver.add_with_velocity(vertex, velocity);
+            assert_eq!(solver.velocity(id), Some(velocity));
+            run(&mut solver, 30);
+            let moved = solver.get(id).unwrap().position - cgmath::vec2(100.0, 100.0);
+            let error = moved - velocity * 0.5;
+            assert!(
+                error.x.abs() < 1e-2 && error.y.abs() < 1e-2,
+                "{integrator:?} with {sub_steps} sub-steps moved {moved:?}"
+            );
+            let measured = solver.velocity(id).unwrap() - velocity;
+            assert!(measured.x.abs() < 1e-2 && measured.y.abs() < 1e-2);
+
+            solver.set_velocity(id, cgmath::vec2(0.0, 0.0));
+            solver.update(DT);
+            let stopped = solver.velocity(id).unwrap();
+            assert!(stopped.x.abs() < 1e-3 && stopped.y.abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn sub_steps_can_be_changed_at_runtime() {
+    let mut solver = Solver::new(SimulationConfig::default(), Vec::new());
+    solver.set_sub_steps(2);
+    assert_eq!(solver.config().sub_steps, 2);
+    solver.set_sub_steps(0);
+    assert_eq!(solver.config().sub_steps, 1);
+}
+
+fn slide(friction: f32) -> f32 {
+    let config = SimulationConfig {
+        wall_friction: 1.0,
+        ..SimulationConfig::default()
+    };
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut vertex = particle(20.0, 1.0).with_friction(friction);
+    vertex.set_velocity(cgmath::vec2(50.0, 0.0), sub_dt);
+    let mut solver = Solver::new(config, vec![vertex]);
+    run(&mut solver, 60);
+    solver.get_objects()[0].velocity(sub_dt).x
+}
+
+#[test]
+fn wall_friction_stops_sliding_particles() {
+    assert!((slide(0.0) - 50.0).abs() < 0.1);
+    // mu * g = 500 per second, so a 50 per second slide stops within 0.1s.
+    assert!(slide(0.25).abs() < 1e-3);
+}
+
+#[test]
+fn damping_and_drag_slow_particles_down() {
+    let config = SimulationConfig {
+        linear_damping: 1.0,
+        air_drag: 0.01,
+        ..floating()
+    };
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut vertex = particle(50.0, 150.0);
+    vertex.set_velocity(cgmath::vec2(100.0, 0.0), sub_dt);
+    let mut solver = Solver::new(config, vec![vertex]);
+    let mut speed = 100.0;
+    for _ in 0..30 {
+        solver.update(DT);
+        let velocity = solver.get_objects()[0].velocity(sub_dt);
+        assert!(velocity.x < speed && velocity.x > 0.0);
+        speed = velocity.x;
+    }
+    // Pure linear damping alone would leave 100 * e^-0.5 = 60.
+    assert!(speed < 60.0);
+}
+
+fn bounce_height(restitution: f32, wall_restitution: Walls) -> f32 {
+    let config = SimulationConfig {
+        wall_restitution,
+        ..SimulationConfig::default()
+    };
+    let vertex = particle(150.0, 101.0).with_restitution(restitution);
+    let mut solver = Solver::new(config, vec![vertex]);
+    let mut last_y: f32 = 100.0;
+    let mut bounced = false;
+    let mut height: f32 = 0.0;
+    for _ in 0..120 {
+        solver.update(DT);
+        let y = solver.get_objects()[0].position.y - 1.0;
+        bounced |= y > last_y;
+        if bounced {
+            height = height.max(y);
+        }
+        last_y = y;
+    }
+    height
+}
+
+#[test]
+fn restitution_makes_particles_bounce() {
+    // A drop from 100 keeps restitution^2 of its height.
+    let rubber = bounce_height(0.8, Walls::default());
+    assert!((rubber - 64.0).abs() < 5.0, "rubber bounced to {rubber}");
+    let trampoline = bounce_height(0.0, Walls::all(0.8));
+    assert!((trampoline - rubber).abs() < 1e-3);
+    let sand = bounce_height(0.0, Walls::default());
+    assert!(sand < 1e-3, "sand bounced to {sand}");
+}
+
+#[test]
+fn elastic_particles_exchange_velocities() {
+    let config = floating();
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut lhs = particle(140.0, 150.0).with_restitution(1.0);
+    lhs.set_velocity(cgmath::vec2(60.0, 0.0), sub_dt);
+    let rhs = particle(160.0, 150.0).with_restitution(1.0);
+    let mut solver = Solver::new(config, vec![lhs, rhs]);
+    run(&mut solver, 30);
+    let objects = solver.get_objects();
+    assert!(objects[0].velocity(sub_dt).x.abs() < 1.0);
+    assert!((objects[1].velocity(sub_dt).x - 60.0).abs() < 1.0);
+}
+
+#[test]
+fn pinned_particles_hold_still_and_block_others() {
+    let anchor = particle(150.0, 150.0).with_motion(Motion::Pinned);
+    let falling = particle(150.0, 160.0);
+    let mut solver = Solver::new(SimulationConfig::default(), vec![anchor, falling]);
+    run(&mut solver, 60);
+    let objects = solver.get_objects();
+    assert_eq!(objects[0].position, cgmath::vec2(150.0, 150.0));
+    assert!((objects[1].position.y - 152.0).abs() < 0.1);
+}
+
+#[test]
+fn kinematic_particles_follow_their_path() {
+    let config = floating();
+    let sub_dt = DT / config.sub_steps as f32;
+    let platform = particle(0.0, 0.0).with_radius(5.0);
+    let ball = particle(120.0, 150.0);
+    let mut solver = Solver::new(config, vec![platform, ball]);
+    let (platform, ball) = (solver.id(0), solver.id(1));
+    solver.set_path(platform, |t| cgmath::vec2(100.0 + 30.0 * t, 150.0));
+    assert_eq!(
+        solver.get(platform).unwrap().position,
+        cgmath::vec2(100.0, 150.0)
+    );
+
+    run(&mut solver, 60);
+
+    let platform = solver.get(platform).unwrap();
+    assert!((solver.time() - 1.0).abs() < 1e-4);
+    assert!((platform.position.x - 130.0).abs() < 1e-3);
+    assert!((platform.velocity(sub_dt).x - 30.0).abs() < 0.1);
+    // The platform pushed the ball ahead of itself.
+    assert!(solver.get(ball).unwrap().position.x >= 136.0 - 1e-3);
+}
+
+fn periodic_config() -> SimulationConfig {
+    SimulationConfig {
+        periodic: Periodic { x: true, y: true },
+        ..floating()
+    }
+}
+
+#[test]
+fn periodic_particles_reenter_on_the_other_side() {
+    let config = periodic_config();
+    let sub_dt = DT / config.sub_steps as f32;
+    let mut object = particle(290.0, 10.0);
+    object.set_velocity(cgmath::vec2(600.0, -600.0), sub_dt);
+    let mut solver = Solver::new(config, vec![object]);
+    run(&mut solver, 3);
+    let object = &solver.get_objects()[0];
+    assert!(
+        (object.position.x - 20.0).abs() < 0.01,
+        "{:?}",
+        object.position
+    );
+    assert!(
+        (object.position.y - 280.0).abs() < 0.01,
+        "{:?}",
+        object.position
+    );
+    let velocity = object.velocity(sub_dt);
+    assert!((velocity.x - 600.0).abs() < 0.1, "{velocity:?}");
+}
+
+#[test]
+fn periodic_collisions_cross_the_seam() {
+    let objects = vec![particle(0.5, 150.0), particle(299.7, 150.0)];
+    let mut solver = Solver::new(periodic_config(), objects);
+    solver.update(DT);
+    let objects = solver.get_objects();
+    // Moving apart across the seam means the left one goes right and the right one left.
+    let gap = objects[0].position.x + 300.0 - objects[1].position.x;
+    assert!(gap >= 2.0 - 1e-3, "{gap}");
+    assert!(objects[0].position.x > 0.5);
+    assert!(objects[1].position.x < 299.7);
+}
+
+#[test]
+fn integrators_keep_spring_energy_bounded() {
+    let center = cgmath::vec2(150.0, 150.0);
+    let stiffness = 400.0;
+    for integrator in [
+        Integrator::PositionVerlet,
+        Integrator::VelocityVerlet,
+        Integrator::SemiImplicitEuler,
+    ] {
+        let config = floating();
+        let sub_dt = DT / config.sub_steps as f32;
+        let mut solver = Solver::new(config, Vec::new());
+        // A force that depends on the position, unlike gravity.
+        solver.add_force_field(ForceField::custom(move |object, _| {
+            (center - object.position) * stiffness
+        }));
+        let vertex = particle(center.x + 10.0, center.y).with_integrator(integrator);
+        let id = solver.add(vertex);
+        let energy = |solver: &Solver| {
+            let offset = solver.get(id).unwrap().position - center;
+            let velocity = solver.get(id).unwrap().velocity(sub_dt);
+            0.5 * velocity.magnitude2() + 0.5 * stiffness * offset.magnitude2()
+        };
+        let initial = energy(&solver);
+        for _ in 0..600 {
+            solver.update(DT);
+            let current = energy(&solver);
+            assert!(
+                (current - initial).abs() < 0.1 * initial,
+                "{integrator:?}: energy {current} from {initial}"
+            );
+        }
+    }
+}